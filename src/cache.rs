@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::checksum::{self, Checksum};
+use crate::download;
+use crate::global;
+use crate::metadata;
+
+pub struct CacheEntry {
+    pub checksum: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+fn files_dir() -> Result<PathBuf> {
+    Ok(global::mcs_home()?.join("cache").join("files"))
+}
+
+// Files live at cache/files/<algorithm>/<hash>/<original filename>
+fn entry_dir(checksum: &Checksum) -> Result<PathBuf> {
+    Ok(files_dir()?
+        .join(checksum.algorithm.to_string())
+        .join(&checksum.hex))
+}
+
+// Files without a published hash are keyed by their URL instead, under cache/files/url/
+fn url_entry_dir(url: &str) -> Result<PathBuf> {
+    Ok(files_dir()?
+        .join("url")
+        .join(format!("{:x}", Sha256::digest(url.as_bytes()))))
+}
+
+fn file_in(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.is_file())
+}
+
+// Hardlinks when source and destination share a filesystem, copies otherwise
+fn link_or_copy(src: &Path, dest: &Path) -> Result<()> {
+    if fs::hard_link(src, dest).is_err() {
+        fs::copy(src, dest).context(format!("Failed to copy {:?} to {:?}", src, dest))?;
+    }
+    Ok(())
+}
+
+fn use_cached(cached: &Path, dest: &Path) -> Result<()> {
+    let temp = download::temp_path(dest);
+    let _ = fs::remove_file(&temp);
+    link_or_copy(cached, &temp)?;
+    fs::rename(&temp, dest).context(format!("Failed to move file into {:?}", dest))?;
+    touch(cached);
+    download::status(format!(
+        "✓ Using cached {}",
+        dest.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    Ok(())
+}
+
+// Places the file with the given checksum at `dest`, from the cache when possible.
+// Files without a known checksum are always downloaded when online, and only served
+// from the cache (by URL) in offline mode.
+pub fn fetch(client: &Client, url: &str, dest: &Path, expected: Option<&Checksum>) -> Result<()> {
+    let filename = dest.file_name().unwrap_or_default().to_string_lossy();
+
+    let Some(expected) = expected else {
+        let dir = url_entry_dir(url)?;
+        if metadata::is_offline() {
+            let cached = file_in(&dir).ok_or_else(|| metadata::offline_error(&filename))?;
+            return use_cached(&cached, dest);
+        }
+        download::download(client, url, dest, None)?;
+        let _ = fs::remove_dir_all(&dir);
+        if let Err(e) = insert(dest, &dir) {
+            println!("⚠ Couldn't add {:?} to the download cache: {}", dest, e);
+        }
+        return Ok(());
+    };
+
+    let dir = entry_dir(expected)?;
+    if let Some(cached) = file_in(&dir) {
+        // Hardlinked copies share data with the cache, so re-check before trusting it
+        if checksum::verify_file(&cached, expected).is_ok() {
+            return use_cached(&cached, dest);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    if metadata::is_offline() {
+        return Err(metadata::offline_error(&filename));
+    }
+
+    download::download(client, url, dest, Some(expected))?;
+
+    // Failing to populate the cache shouldn't fail the download itself
+    if let Err(e) = insert(dest, &dir) {
+        println!("⚠ Couldn't add {:?} to the download cache: {}", dest, e);
+    }
+
+    Ok(())
+}
+
+fn insert(path: &Path, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let target = dir.join(path.file_name().unwrap_or_default());
+    if !target.exists() {
+        link_or_copy(path, &target)?;
+    }
+    Ok(())
+}
+
+fn touch(path: &Path) {
+    if let Ok(file) = File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+pub fn entries() -> Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    let Ok(algorithms) = fs::read_dir(files_dir()?) else {
+        return Ok(entries);
+    };
+
+    for algorithm in algorithms.filter_map(|e| e.ok()) {
+        for hash in fs::read_dir(algorithm.path())?.filter_map(|e| e.ok()) {
+            for file in fs::read_dir(hash.path())?.filter_map(|e| e.ok()) {
+                let metadata = file.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                entries.push(CacheEntry {
+                    checksum: format!(
+                        "{}:{}",
+                        algorithm.file_name().to_string_lossy(),
+                        hash.file_name().to_string_lossy()
+                    ),
+                    path: file.path(),
+                    size: metadata.len(),
+                    last_used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+            }
+        }
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    Ok(entries)
+}
+
+fn remove_entry(entry: &CacheEntry) -> Result<()> {
+    let dir = entry
+        .path
+        .parent()
+        .context("Invalid cache entry")?;
+    fs::remove_dir_all(dir).context(format!("Failed to remove {:?}", dir))
+}
+
+// Returns how many entries were removed and how many bytes that freed
+pub fn clean() -> Result<(usize, u64)> {
+    let entries = entries()?;
+    let freed = entries.iter().map(|e| e.size).sum();
+    let dir = files_dir()?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context(format!("Failed to remove {:?}", dir))?;
+    }
+    Ok((entries.len(), freed))
+}
+
+pub fn prune(older_than: Duration) -> Result<(usize, u64)> {
+    let cutoff = SystemTime::now()
+        .checked_sub(older_than)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut removed = 0;
+    let mut freed = 0;
+    for entry in entries()? {
+        if entry.last_used < cutoff {
+            remove_entry(&entry)?;
+            removed += 1;
+            freed += entry.size;
+        }
+    }
+    Ok((removed, freed))
+}
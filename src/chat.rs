@@ -0,0 +1,61 @@
+use serde_json::Value;
+
+// Drops Minecraft's "§x" formatting codes
+pub fn strip_formatting(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Plain text of a chat component: a string, an array of components, or an object
+// with "text", "translate" and "extra" children
+pub fn plain_text(component: &Value) -> String {
+    match component {
+        Value::String(text) => strip_formatting(text),
+        Value::Array(parts) => parts.iter().map(plain_text).collect(),
+        Value::Object(fields) => {
+            let mut text = fields
+                .get("text")
+                .or_else(|| fields.get("translate"))
+                .map(plain_text)
+                .unwrap_or_default();
+            if let Some(extra) = fields.get("extra") {
+                text.push_str(&plain_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn strips_formatting() {
+        assert_eq!(strip_formatting("§aHello §l§oworld§r!"), "Hello world!");
+        assert_eq!(strip_formatting("trailing §"), "trailing ");
+    }
+
+    #[test]
+    fn components() {
+        assert_eq!(plain_text(&json!("§6Gold")), "Gold");
+        assert_eq!(plain_text(&json!(["A ", {"text": "B"}, " C"])), "A B C");
+        assert_eq!(
+            plain_text(&json!({"text": "A ", "color": "red", "extra": [{"text": "§lB", "extra": [" C"]}]})),
+            "A B C"
+        );
+        assert_eq!(plain_text(&json!({"translate": "multiplayer.disconnect"})), "multiplayer.disconnect");
+        assert_eq!(plain_text(&json!({"extra": ["only extra"]})), "only extra");
+        assert_eq!(plain_text(&json!(42)), "");
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+const LOCK_FILE: &str = "mcs.lock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Algorithm::Sha1 => write!(f, "sha1"),
+            Algorithm::Sha256 => write!(f, "sha256"),
+            Algorithm::Sha512 => write!(f, "sha512"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub hex: String,
+}
+
+impl Checksum {
+    pub fn new(algorithm: Algorithm, hex: &str) -> Self {
+        Self {
+            algorithm,
+            hex: hex.trim().to_ascii_lowercase(),
+        }
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+impl std::str::FromStr for Checksum {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (algorithm, hex) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid checksum '{}'", s))?;
+        let algorithm = match algorithm {
+            "sha1" => Algorithm::Sha1,
+            "sha256" => Algorithm::Sha256,
+            "sha512" => Algorithm::Sha512,
+            other => return Err(anyhow!("Unknown checksum algorithm '{}'", other)),
+        };
+        Ok(Checksum::new(algorithm, hex))
+    }
+}
+
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> Result<String> {
+    let mut file = File::open(path).context(format!("Failed to read {:?}", path))?;
+
+    let hex = match algorithm {
+        Algorithm::Sha1 => {
+            let mut hasher = Sha1::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+        Algorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+        Algorithm::Sha512 => {
+            let mut hasher = Sha512::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+    };
+
+    Ok(hex)
+}
+
+// Deletes the file when it doesn't match, so a bad download never stays in place
+pub fn verify_file(path: &Path, expected: &Checksum) -> Result<()> {
+    let actual = hash_file(path, expected.algorithm)?;
+
+    if actual != expected.hex {
+        let _ = fs::remove_file(path);
+        return Err(anyhow!(
+            "Checksum mismatch for {}: expected {} {}, got {}. The file was deleted.",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            expected.algorithm,
+            expected.hex,
+            actual
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LockFile {
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+fn load_lock(server_dir: &Path) -> Result<LockFile> {
+    let lock_path = server_dir.join(LOCK_FILE);
+    if !lock_path.exists() {
+        return Ok(LockFile::default());
+    }
+    let contents = fs::read_to_string(&lock_path)
+        .context(format!("Failed to read {:?}", lock_path))?;
+    toml::from_str(&contents).context(format!("Failed to parse {:?}", lock_path))
+}
+
+fn save_lock(server_dir: &Path, lock: &LockFile) -> Result<()> {
+    let lock_path = server_dir.join(LOCK_FILE);
+    let contents = toml::to_string_pretty(lock).context("Failed to serialize lock file")?;
+    fs::write(
+        &lock_path,
+        format!("# Hashes of files downloaded by mcs. Do not edit.\n\n{}", contents),
+    )
+    .context(format!("Failed to write {:?}", lock_path))
+}
+
+fn lock_key(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+// Records the verified hash of a file, keyed by its path relative to the server directory
+pub fn record(server_dir: &Path, relative: &Path, checksum: &Checksum) -> Result<()> {
+    let mut lock = load_lock(server_dir)?;
+    lock.files.insert(lock_key(relative), checksum.to_string());
+    // Drop entries for files that were removed or disabled since
+    lock.files.retain(|name, _| server_dir.join(name).exists());
+    save_lock(server_dir, &lock)
+}
+
+// Files whose current contents no longer match the hash recorded when they were downloaded
+pub fn modified_files(server_dir: &Path) -> Result<Vec<String>> {
+    let lock = load_lock(server_dir)?;
+    let mut modified = Vec::new();
+
+    for (name, recorded) in &lock.files {
+        let path = server_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let Ok(expected) = recorded.parse::<Checksum>() else {
+            continue;
+        };
+        if hash_file(&path, expected.algorithm)? != expected.hex {
+            modified.push(name.clone());
+        }
+    }
+
+    Ok(modified)
+}
@@ -1,14 +1,17 @@
 use anyhow::{Result, anyhow};
 use std::fs;
-use std::path::PathBuf;
-use crate::config::{ServerConfig, ServerType};
-use crate::prompt::prompt_for_config;
+use std::path::Path;
+use crate::config::{FlagPreset, ServerConfig, ServerType};
+use crate::prompt::{prompt_for_config, prompt_for_query, prompt_for_rcon};
 use crate::setup::{create_start_scripts, create_eula};
 use crate::{paper, vanilla, fabric, spigot, forge};
-use crate::modrinth;
+use crate::modrinth::{self, ModrinthFile};
+use crate::{cache, chat, checksum, compat, control, jar, java, overlay, parallel, ping};
+use crate::{ports, properties, query, rcon, registry, supervisor, system, units, upgrade};
+use crate::units::MemorySize;
 use inquire::Select;
 
-pub fn create_new_server(path: &PathBuf) -> Result<()> {
+pub fn create_new_server(path: &Path, preset: Option<ServerConfig>) -> Result<()> {
     if !path.exists() {
         fs::create_dir_all(path)?;
     }
@@ -24,7 +27,10 @@ pub fn create_new_server(path: &PathBuf) -> Result<()> {
         ));
     }
 
-    let config = prompt_for_config()?;
+    let config = match preset {
+        Some(config) => config,
+        None => prompt_for_config()?,
+    };
     config.save(path)?;
 
     setup_server(path, &config)?;
@@ -43,9 +49,28 @@ pub fn reconfigure_server() -> Result<()> {
         return Err(anyhow!("No mcs.toml found in current directory. Run 'mcs new <path>' first."));
     }
 
-    let config = prompt_for_config()?;
+    let prompted = prompt_for_config()?;
+    // Only the prompted settings change; everything else in mcs.toml is kept
+    let config = match ServerConfig::load(&current_dir) {
+        Ok(previous) => ServerConfig {
+            version: prompted.version,
+            server_type: prompted.server_type,
+            memory: prompted.memory,
+            flags: prompted.flags,
+            recommended_flags: prompted.recommended_flags,
+            ..previous
+        },
+        Err(_) => prompted,
+    };
     config.save(&current_dir)?;
 
+    let props = properties::load(&current_dir)?;
+    let rcon_enabled = props.get("enable-rcon").map(String::as_str) == Some("true");
+    rcon::configure(&current_dir, prompt_for_rcon(rcon_enabled)?)?;
+    let query_enabled = props.get("enable-query").map(String::as_str) == Some("true");
+    query::configure(&current_dir, prompt_for_query(query_enabled)?)?;
+
+    compat::resolve_incompatible(&current_dir, &config)?;
     setup_server(&current_dir, &config)?;
 
     println!("\n✓ Server reconfigured successfully!");
@@ -57,6 +82,11 @@ pub fn apply_config() -> Result<()> {
     let current_dir = std::env::current_dir()?;
     let config = ServerConfig::load(&current_dir)?;
 
+    for name in checksum::modified_files(&current_dir)? {
+        println!("⚠ {} has changed since it was downloaded (hash mismatch)", name);
+    }
+
+    compat::resolve_incompatible(&current_dir, &config)?;
     setup_server(&current_dir, &config)?;
 
     println!("\n✓ Configuration applied successfully!");
@@ -64,7 +94,7 @@ pub fn apply_config() -> Result<()> {
     Ok(())
 }
 
-pub fn add_content(name: &str) -> Result<()> {
+pub fn add_content(names: &[String]) -> Result<()> {
     let current_dir = std::env::current_dir()?;
 
     if !current_dir.join("mcs.toml").exists() {
@@ -85,16 +115,77 @@ pub fn add_content(name: &str) -> Result<()> {
         }
     };
 
-    // Extract slug from Modrinth URL or use name as-is
-    let slug = if name.contains("modrinth.com") {
-        name.trim_end_matches('/')
-            .split('/')
-            .last()
-            .unwrap_or(name)
-    } else {
-        name
-    };
+    // Extract slugs from Modrinth URLs or use names as-is
+    let slugs: Vec<String> = names
+        .iter()
+        .map(|name| {
+            if name.contains("modrinth.com") {
+                name.trim_end_matches('/')
+                    .split('/')
+                    .next_back()
+                    .unwrap_or(name)
+                    .to_string()
+            } else {
+                name.clone()
+            }
+        })
+        .collect();
+
+    // Fetch every project's versions up front and in parallel. The picker below then
+    // reads them from the metadata cache; errors resurface there for the right name.
+    if slugs.len() > 1 {
+        println!("\n⟳ Fetching versions for {} projects...", slugs.len());
+        let game_version = config.version.clone();
+        parallel::map(slugs.clone(), move |slug| {
+            modrinth::get_project_versions(&slug, loader, &game_version).map(|_| ())
+        })?;
+    }
+
+    let dest_dir = current_dir.join(dest_subdir);
+    let mut files: Vec<ModrinthFile> = Vec::new();
+    for (name, slug) in names.iter().zip(&slugs) {
+        let file = pick_file(name, slug, &config, project_type, loader, &dest_dir)?;
+        if !files.iter().any(|f| f.filename == file.filename) {
+            files.push(file);
+        }
+    }
+
+    // Create destination dir and download
+    fs::create_dir_all(&dest_dir)?;
+
+    match files.as_slice() {
+        [file] => println!("\n↓ Downloading {}...", file.filename),
+        _ => println!("\n↓ Downloading {} files...", files.len()),
+    }
+    let downloads = files
+        .iter()
+        .map(|file| (file.clone(), dest_dir.join(&file.filename)))
+        .collect();
+    let result = modrinth::download_files(downloads);
+
+    // Record and report whatever made it, even when some downloads failed
+    for file in &files {
+        if !dest_dir.join(&file.filename).exists() {
+            continue;
+        }
+        if let Some(hash) = file.checksum() {
+            checksum::record(&current_dir, &Path::new(dest_subdir).join(&file.filename), &hash)?;
+        }
+        println!("✓ Installed {}", file.filename);
+    }
+
+    result
+}
 
+// Resolves one name to a Modrinth project and lets the user pick which version to install
+fn pick_file(
+    name: &str,
+    slug: &str,
+    config: &ServerConfig,
+    project_type: &str,
+    loader: &str,
+    dest_dir: &Path,
+) -> Result<ModrinthFile> {
     println!("\n⟳ Fetching versions for '{}'...", slug);
 
     // Try exact slug lookup; fall back to search if nothing found
@@ -165,7 +256,7 @@ pub fn add_content(name: &str) -> Result<()> {
             .map(|p| (p.title, p.slug))
             .unwrap_or_else(|| (dep.project_id.clone(), dep.project_id.clone()));
 
-        let already_installed = fs::read_dir(current_dir.join(dest_subdir))
+        let already_installed = fs::read_dir(dest_dir)
             .map(|entries| {
                 entries
                     .filter_map(|e| e.ok())
@@ -179,27 +270,162 @@ pub fn add_content(name: &str) -> Result<()> {
     }
 
     // Find primary file (fall back to first file if none marked primary)
-    let file = selected_version
+    selected_version
         .files
         .iter()
         .find(|f| f.primary)
         .or_else(|| selected_version.files.first())
-        .ok_or_else(|| anyhow!("No downloadable file found for this version"))?;
+        .cloned()
+        .ok_or_else(|| anyhow!("No downloadable file found for this version"))
+}
 
-    // Create destination dir and download
-    let dest_dir = current_dir.join(dest_subdir);
-    fs::create_dir_all(&dest_dir)?;
+pub fn list_content() -> Result<()> {
+    let current_dir = std::env::current_dir()?;
 
-    let dest_path = dest_dir.join(&file.filename);
-    println!("\n↓ Downloading {}...", file.filename);
-    modrinth::download_file(&file.url, &dest_path)?;
+    if !current_dir.join("mcs.toml").exists() {
+        return Err(anyhow!(
+            "No mcs.toml found in current directory. Run 'mcs new <path>' first."
+        ));
+    }
 
-    println!("✓ Installed {}", file.filename);
+    let config = ServerConfig::load(&current_dir)?;
+
+    let dest_subdir = config
+        .server_type
+        .content_dir()
+        .ok_or_else(|| anyhow!("Vanilla servers don't support mods or plugins."))?;
+
+    let jars = compat::installed_jars(&current_dir, &config);
+    let modified = checksum::modified_files(&current_dir)?;
+
+    println!("▶ Installed {} ({})\n", dest_subdir, jars.len());
+
+    for path in &jars {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        match jar::inspect(path) {
+            Ok(Some(meta)) => {
+                println!(
+                    "  {} {} - {}",
+                    meta.name.as_deref().unwrap_or(&meta.id),
+                    meta.version.as_deref().unwrap_or("?"),
+                    filename
+                );
+                match (&meta.minecraft, &meta.loader) {
+                    (Some(mc), Some(loader)) => {
+                        println!("    {} for Minecraft {} (loader {})", meta.kind, mc, loader)
+                    }
+                    (Some(mc), None) => println!("    {} for Minecraft {}", meta.kind, mc),
+                    _ => println!("    {}", meta.kind),
+                }
+                let required: Vec<String> = meta
+                    .dependencies
+                    .iter()
+                    .filter(|d| d.required)
+                    .map(|d| match &d.version {
+                        Some(v) if v != "*" => format!("{} {}", d.id, v),
+                        _ => d.id.clone(),
+                    })
+                    .collect();
+                if !required.is_empty() {
+                    println!("    requires: {}", required.join(", "));
+                }
+            }
+            Ok(None) => println!("  {} (no mod/plugin metadata)", filename),
+            Err(e) => println!("  ⚠ {} ({})", filename, e),
+        }
+
+        if modified.contains(&format!("{}/{}", dest_subdir, filename)) {
+            println!("    ⚠ changed since it was downloaded (hash mismatch)");
+        }
+    }
 
     Ok(())
 }
 
-fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
+pub fn upgrade_server(target_version: &str, plan_only: bool, force: bool) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+
+    if !current_dir.join("mcs.toml").exists() {
+        return Err(anyhow!(
+            "No mcs.toml found in current directory. Run 'mcs new <path>' first."
+        ));
+    }
+
+    let config = ServerConfig::load(&current_dir)?;
+
+    if config.version == target_version {
+        return Err(anyhow!("Server is already on Minecraft {}.", target_version));
+    }
+
+    let plan = upgrade::build_plan(&current_dir, &config, target_version)?;
+    upgrade::print_plan(&config, &plan);
+
+    if plan_only {
+        println!("→ Run 'mcs upgrade --to {} --execute' to apply this plan", target_version);
+        return Ok(());
+    }
+
+    let blocked = plan.blocked();
+    if blocked > 0 && !force {
+        return Err(anyhow!(
+            "{} file(s) are blocked. Re-run with --force to disable them and upgrade anyway.",
+            blocked
+        ));
+    }
+
+    println!();
+    upgrade::execute(&current_dir, &config, &plan, setup_server)?;
+
+    println!("\n✓ Upgraded to {} {}!", config.server_type, target_version);
+
+    Ok(())
+}
+
+pub fn list_cache() -> Result<()> {
+    let entries = cache::entries()?;
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+
+    println!(
+        "▶ Download cache ({} files, {})\n",
+        entries.len(),
+        units::format_size(total)
+    );
+
+    for entry in &entries {
+        let age = entry.last_used.elapsed().unwrap_or_default();
+        println!(
+            "  {} {} (used {} ago)",
+            entry.path.file_name().unwrap_or_default().to_string_lossy(),
+            units::format_size(entry.size),
+            units::format_duration(age)
+        );
+        println!("    {}", entry.checksum);
+    }
+
+    Ok(())
+}
+
+pub fn clean_cache() -> Result<()> {
+    let (removed, freed) = cache::clean()?;
+    println!("✓ Removed {} cached files ({})", removed, units::format_size(freed));
+    Ok(())
+}
+
+pub fn prune_cache(older_than: &str) -> Result<()> {
+    let max_age = units::parse_duration(older_than)?;
+    let (removed, freed) = cache::prune(max_age)?;
+    println!(
+        "✓ Removed {} cached files not used in {} ({})",
+        removed,
+        older_than,
+        units::format_size(freed)
+    );
+    Ok(())
+}
+
+fn setup_server(path: &Path, config: &ServerConfig) -> Result<()> {
+    let config = &java::ensure(path, config)?;
+
     match config.server_type {
         ServerType::Paper => {
             paper::setup_server(path, config)?;
@@ -227,5 +453,403 @@ fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
             create_eula(path)?;
         }
     }
+    properties::apply(path, config)?;
+    overlay::apply(path, config)?;
+    registry::register(path)?;
+    ports::check(path, config)?;
+    Ok(())
+}
+
+pub fn run_server(stop_timeout: &str) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+    let stop_timeout = units::parse_duration(stop_timeout)?;
+
+    println!("▶ Starting {} {}\n", config.server_type, config.version);
+    supervisor::run(&current_dir, &config, stop_timeout, true)
+}
+
+pub fn start_server(detach: bool, stop_timeout: &str) -> Result<()> {
+    if !detach {
+        return run_server(stop_timeout);
+    }
+
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+    units::parse_duration(stop_timeout)?;
+
+    println!("▶ Starting {} {} in the background\n", config.server_type, config.version);
+    control::start_detached(&current_dir, stop_timeout)
+}
+
+// The detached supervisor started by `mcs start --detach`
+pub fn supervise_detached(stop_timeout: &str) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+    let stop_timeout = units::parse_duration(stop_timeout)?;
+    supervisor::run(&current_dir, &config, stop_timeout, false)
+}
+
+pub fn attach_console() -> Result<()> {
+    control::attach(&std::env::current_dir()?)
+}
+
+pub fn stop_server(timeout: &str) -> Result<()> {
+    let timeout = units::parse_duration(timeout)?;
+    control::stop(&std::env::current_dir()?, timeout)
+}
+
+pub fn server_status() -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+
+    println!("▶ {} {}\n", config.server_type, config.version);
+
+    let Some(status) = control::status(&current_dir)? else {
+        println!("  State     stopped");
+        return Ok(());
+    };
+
+    let Some(server_pid) = status.server_pid else {
+        println!("  State     restarting");
+        println!("  PID       supervisor {}", status.pid);
+        return Ok(());
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let uptime = std::time::Duration::from_secs(now.saturating_sub(status.started));
+    let memory = system::process_memory(server_pid)
+        .map(units::format_size)
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // A live process can still be starting up; only a ping shows it accepts players
+    let players = ping::local_address(&current_dir)
+        .and_then(|(host, port)| ping::ping(&host, port))
+        .map(|ping| format!("{}/{}", ping.online, ping.max))
+        .unwrap_or_else(|_| "not accepting connections yet".to_string());
+
+    println!("  State     running");
+    println!("  PID       {} (supervisor {})", server_pid, status.pid);
+    println!("  Uptime    {}", units::format_duration(uptime));
+    println!("  Memory    {}", memory);
+    println!("  Players   {}", players);
+    println!("  Restarts  {}", status.restarts);
+
+    Ok(())
+}
+
+pub fn exec_command(command: &[String], interactive: bool) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let (host, port, password) = rcon::local_settings(&current_dir)?;
+    let mut rcon = rcon::Rcon::connect(&host, port, &password)?;
+
+    if interactive {
+        println!(
+            "■ Connected to {}. Type exit or press Ctrl+D to quit.\n",
+            rcon::display_address(&host, port)
+        );
+        return rcon::repl(&mut rcon);
+    }
+
+    let output = rcon.exec(&command.join(" "))?;
+    if !output.is_empty() {
+        println!("{}", chat::strip_formatting(output.trim_end()));
+    }
+
+    Ok(())
+}
+
+pub fn ping_server(address: Option<&str>, json: bool) -> Result<()> {
+    let (host, port) = match address {
+        Some(address) => ping::parse_address(address, ping::DEFAULT_PORT)?,
+        None => {
+            let current_dir = std::env::current_dir()?;
+            if !current_dir.join("mcs.toml").exists() {
+                return Err(anyhow!("No mcs.toml found in current directory. Pass an address, e.g. 'mcs ping example.com'."));
+            }
+            ping::local_address(&current_dir)?
+        }
+    };
+
+    let status = ping::ping(&host, port)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    println!("▶ {}:{}\n", host, port);
+    println!("  Version   {} (protocol {})", status.version, status.protocol);
+    if status.players.is_empty() {
+        println!("  Players   {}/{}", status.online, status.max);
+    } else {
+        println!("  Players   {}/{}: {}", status.online, status.max, status.players.join(", "));
+    }
+    for (i, line) in status.motd.lines().enumerate() {
+        println!("  {:<9} {}", if i == 0 { "MOTD" } else { "" }, line.trim());
+    }
+    println!("  Latency   {} ms", status.latency_ms);
+    if status.legacy {
+        println!("\n■ The server only answered the legacy (pre-1.7) ping");
+    }
+
+    Ok(())
+}
+
+pub fn query_server(address: Option<&str>) -> Result<()> {
+    let (host, port) = match address {
+        Some(address) => ping::parse_address(address, ping::DEFAULT_PORT)?,
+        None => {
+            let current_dir = std::env::current_dir()?;
+            if !current_dir.join("mcs.toml").exists() {
+                return Err(anyhow!("No mcs.toml found in current directory. Pass an address, e.g. 'mcs query example.com'."));
+            }
+            query::local_address(&current_dir)?
+        }
+    };
+
+    let result = query::query(&host, port)?;
+
+    println!("▶ {}:{}\n", host, port);
+    println!("  MOTD       {}", chat::strip_formatting(&result.motd));
+    println!("  Game type  {}", result.game_type);
+    println!("  Version    {}", result.version);
+    println!("  Map        {}", result.map);
+    println!("  Players    {}/{}", result.online, result.max);
+
+    // "Paper on 1.21.1: WorldEdit 7.3.4; LuckPerms 5.4.1", or just the server name
+    let (server, plugins) = match result.plugins.split_once(':') {
+        Some((server, plugins)) => (
+            server.trim(),
+            plugins.split(';').map(str::trim).filter(|p| !p.is_empty()).collect(),
+        ),
+        None => (result.plugins.trim(), Vec::new()),
+    };
+    if !server.is_empty() {
+        println!("  Server     {}", server);
+    }
+
+    if !plugins.is_empty() {
+        println!("\n▶ Plugins ({})\n", plugins.len());
+        for plugin in plugins {
+            println!("  {}", plugin);
+        }
+    }
+
+    if !result.players.is_empty() {
+        println!("\n▶ Players ({})\n", result.players.len());
+        for player in &result.players {
+            println!("  {}", player);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list_properties() -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+    let mut values = properties::load(&current_dir)?;
+    for (key, value) in &config.properties {
+        values.insert(key.clone(), properties::to_property(key, value)?);
+    }
+
+    if values.is_empty() {
+        println!("■ No server properties yet. They are created when the server first starts.");
+        return Ok(());
+    }
+
+    println!("▶ Server properties\n");
+    let width = values.keys().map(|k| k.len()).max().unwrap_or_default();
+    for (key, value) in &values {
+        // Don't print secrets to the terminal
+        let value = if key == "rcon.password" && !value.is_empty() { "********" } else { value };
+        let managed = if config.properties.contains_key(key) { "  (mcs.toml)" } else { "" };
+        println!("  {:<width$} = {}{}", key, value, managed, width = width);
+    }
+
+    Ok(())
+}
+
+pub fn get_property(key: &str) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = ServerConfig::load(&current_dir)?;
+
+    let value = match config.properties.get(key) {
+        Some(value) => Some(properties::to_property(key, value)?),
+        None => properties::load(&current_dir)?.remove(key),
+    };
+
+    match value {
+        Some(value) => println!("{}", value),
+        None if properties::is_known(key) => {
+            return Err(anyhow!("{} is not set; the server uses its default", key));
+        }
+        None => return Err(anyhow!("Unknown server property '{}'", key)),
+    }
+
+    Ok(())
+}
+
+pub fn set_property(key: &str, value: &str) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let mut config = ServerConfig::load(&current_dir)?;
+
+    properties::validate(key, value)?;
+    if !properties::is_known(key) {
+        println!("⚠ Unknown server property '{}'; setting it anyway", key);
+    }
+
+    config.properties.insert(key.to_string(), properties::to_toml(key, value));
+    config.save(&current_dir)?;
+    properties::update(&current_dir, &[(key, value.to_string())])?;
+
+    println!("✓ Set {} = {}", key, value);
+    if control::is_running(&current_dir) {
+        println!("→ Restart the server for this to take effect");
+    }
+
+    Ok(())
+}
+
+pub fn list_servers() -> Result<()> {
+    let entries = registry::list()?;
+
+    if entries.is_empty() {
+        println!("■ No servers registered yet");
+        println!("→ Servers are registered by 'mcs new' and 'mcs apply'");
+        return Ok(());
+    }
+
+    let width = entries.iter().map(|e| e.name.len()).max().unwrap_or_default();
+    println!("▶ Servers\n");
+    for entry in entries {
+        let (server, state) = if !entry.path.exists() {
+            ("-".to_string(), "missing")
+        } else {
+            let server = ServerConfig::load(&entry.path)
+                .map(|config| format!("{} {}", config.server_type, config.version))
+                .unwrap_or_else(|_| "invalid mcs.toml".to_string());
+            let state = match control::status(&entry.path) {
+                Ok(Some(status)) if status.server_pid.is_some() => "running",
+                Ok(Some(_)) => "restarting",
+                Ok(None) => "stopped",
+                Err(_) => "unknown",
+            };
+            (server, state)
+        };
+        println!("  {:<width$}  {:<16} {:<10} {}", entry.name, server, state, entry.path.display());
+    }
+
+    Ok(())
+}
+
+pub fn list_java() -> Result<()> {
+    let installs = java::detect();
+
+    if installs.is_empty() {
+        println!("■ No Java runtimes found");
+        return Ok(());
+    }
+
+    println!("▶ Java runtimes\n");
+    for install in installs {
+        println!("  Java {:<3} {:<12} {}", install.major, install.version, install.path.display());
+    }
+
+    Ok(())
+}
+
+pub fn tune(apply: bool) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = if current_dir.join("mcs.toml").exists() {
+        Some(ServerConfig::load(&current_dir)?)
+    } else if apply {
+        return Err(anyhow!("No mcs.toml found in current directory. Run 'mcs new <path>' first."));
+    } else {
+        None
+    };
+
+    let total = system::total_memory()
+        .ok_or_else(|| anyhow!("Couldn't detect how much memory this machine has."))?;
+    let cpus = system::cpu_count();
+    let java_major = config
+        .as_ref()
+        .and_then(|c| c.java_path.as_deref())
+        .and_then(java::inspect)
+        .or_else(|| java::detect().into_iter().next())
+        .map(|install| install.major);
+
+    println!("▶ System\n");
+    println!("  Memory: {}", units::format_size(total));
+    println!("  CPUs:   {}", cpus);
+    if let Some(major) = java_major {
+        println!("  Java:   {}", major);
+    }
+
+    // Leave the OS at least 2G (or a fifth of the machine), and stay well below the
+    // 32G compressed pointer limit; Minecraft rarely benefits from more than 16G
+    const GIB: u64 = 1024 * 1024 * 1024;
+    let reserve = (2 * GIB).max(total / 5);
+    let heap_gib = (total.saturating_sub(reserve) / GIB).clamp(1, 16);
+    let memory = MemorySize::from_gib(heap_gib);
+
+    let flags = if heap_gib >= 16 && cpus >= 4 && java_major.is_some_and(|major| major >= 21) {
+        FlagPreset::Zgc
+    } else {
+        match config.as_ref().map(|c| c.flag_preset()) {
+            // Paper's list is Aikar's flags, kept up to date
+            Some(FlagPreset::PaperApi) => FlagPreset::PaperApi,
+            _ => FlagPreset::Aikar,
+        }
+    };
+
+    println!("\n▶ Suggested settings\n");
+    let current = |value: String| match &config {
+        Some(_) => format!(" (currently {})", value),
+        None => String::new(),
+    };
+    println!(
+        "  memory = \"{}\"{}",
+        memory,
+        current(config.as_ref().map(|c| c.max_memory().to_string()).unwrap_or_default())
+    );
+    println!(
+        "  flags = \"{}\"{}",
+        flags,
+        current(config.as_ref().map(|c| c.flag_preset().to_string()).unwrap_or_default())
+    );
+
+    if cpus < 4 {
+        println!(
+            "\n⚠ With {} CPU core(s), garbage collection competes with the server thread. Keep view-distance and player counts modest.",
+            cpus
+        );
+    }
+    if total < 3 * GIB {
+        println!("\n⚠ This machine has little memory for a Minecraft server; expect lag on anything but small worlds.");
+    }
+
+    let Some(mut config) = config else {
+        return Ok(());
+    };
+
+    if !apply {
+        println!("\n→ Run 'mcs tune --apply' to write these to mcs.toml and regenerate the start scripts");
+        return Ok(());
+    }
+
+    config.memory = memory;
+    config.min_memory = None;
+    config.max_memory = None;
+    config.flags = Some(flags);
+    config.save(&current_dir)?;
+    println!();
+    create_start_scripts(&current_dir, &config)?;
+    println!("✓ Applied suggested settings to mcs.toml");
+
     Ok(())
 }
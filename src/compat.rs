@@ -0,0 +1,319 @@
+use anyhow::{Context, Result, anyhow};
+use inquire::Select;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::checksum::{self, Algorithm};
+use crate::config::{ServerConfig, ServerType};
+use crate::jar::{self, JarKind, JarMetadata, file_name};
+use crate::version;
+use crate::{fabric, forge, http, modrinth};
+
+pub struct Incompatibility {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+pub fn installed_jars(path: &Path, config: &ServerConfig) -> Vec<PathBuf> {
+    let Some(dest_subdir) = config.server_type.content_dir() else {
+        return vec![];
+    };
+
+    let mut jars: Vec<_> = fs::read_dir(path.join(dest_subdir))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "jar"))
+                .collect()
+        })
+        .unwrap_or_default();
+    jars.sort();
+    jars
+}
+
+fn loads_kind(server_type: &ServerType, kind: JarKind) -> bool {
+    match server_type {
+        ServerType::Paper => matches!(kind, JarKind::Bukkit | JarKind::PaperPlugin),
+        ServerType::Spigot => kind == JarKind::Bukkit,
+        ServerType::Fabric => kind == JarKind::Fabric,
+        ServerType::Forge => kind == JarKind::Forge,
+        ServerType::Vanilla => false,
+    }
+}
+
+// The loader build setup will install for this config, which is what the loader
+// constraints mods declare have to match. When it can't be looked up (offline with
+// nothing cached), only the Minecraft version is checked.
+fn target_loader(config: &ServerConfig) -> Option<String> {
+    match config.server_type {
+        ServerType::Fabric => fabric::latest_loader(&http::client().ok()?).ok(),
+        ServerType::Forge => forge::build_for(&http::client().ok()?, &config.version).ok(),
+        _ => None,
+    }
+}
+
+// Checks the Minecraft and loader constraints a jar declares
+fn declared_mismatch(meta: &JarMetadata, config: &ServerConfig, loader: Option<&str>) -> Option<String> {
+    let maven = matches!(meta.kind, JarKind::Forge | JarKind::NeoForge);
+
+    if let Some(constraint) = &meta.minecraft
+        && version::matches(constraint, &config.version, maven) == Some(false)
+    {
+        return Some(format!("requires Minecraft {}", constraint));
+    }
+
+    if let (Some(constraint), Some(installed)) = (&meta.loader, loader)
+        && version::matches(constraint, installed, maven) == Some(false)
+    {
+        let loader_name = if maven { "Forge" } else { "Fabric Loader" };
+        return Some(format!("requires {} {} (installed: {})", loader_name, constraint, installed));
+    }
+
+    None
+}
+
+pub fn find_incompatible(path: &Path, config: &ServerConfig) -> Vec<Incompatibility> {
+    check_jars(path, config, target_loader(config).as_deref())
+}
+
+fn check_jars(path: &Path, config: &ServerConfig, loader: Option<&str>) -> Vec<Incompatibility> {
+    let mut incompatible = Vec::new();
+
+    for jar_path in installed_jars(path, config) {
+        let metadata = match jar::inspect(&jar_path) {
+            Ok(metadata) => metadata,
+            // One broken file shouldn't stop the check of the others
+            Err(e) => {
+                incompatible.push(Incompatibility {
+                    path: jar_path,
+                    reason: format!("is unreadable ({})", e.root_cause()),
+                });
+                continue;
+            }
+        };
+
+        let reason = match &metadata {
+            Some(meta) if !loads_kind(&config.server_type, meta.kind) => Some(format!(
+                "is a {}, which {} can't load",
+                meta.kind, config.server_type
+            )),
+            Some(meta) if meta.minecraft.is_some() || meta.loader.is_some() => {
+                declared_mismatch(meta, config, loader)
+            }
+            // Nothing declared in the jar, so fall back to what Modrinth knows about the file.
+            // Lookup failures (offline, unknown file) are not treated as incompatibilities.
+            _ => match checksum::hash_file(&jar_path, Algorithm::Sha1)
+                .ok()
+                .and_then(|hash| modrinth::get_version_from_hash(&hash).ok().flatten())
+            {
+                Some(v) if !v.game_versions.contains(&config.version) => Some(format!(
+                    "is only published for Minecraft {}",
+                    v.game_versions.join(", ")
+                )),
+                _ => None,
+            },
+        };
+
+        if let Some(reason) = reason {
+            incompatible.push(Incompatibility {
+                path: jar_path,
+                reason,
+            });
+        }
+    }
+
+    incompatible
+}
+
+pub fn resolve_incompatible(path: &Path, config: &ServerConfig) -> Result<()> {
+    let Some(dest_subdir) = config.server_type.content_dir() else {
+        return Ok(());
+    };
+
+    let incompatible = find_incompatible(path, config);
+    if incompatible.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n⚠ {} installed file(s) in {} are incompatible with {} {}:",
+        incompatible.len(),
+        dest_subdir,
+        config.server_type,
+        config.version
+    );
+    for item in &incompatible {
+        println!("  • {} {}", file_name(&item.path), item.reason);
+    }
+    println!();
+
+    for item in &incompatible {
+        let mut options = vec!["Update from Modrinth", "Disable", "Keep"];
+
+        loop {
+            let choice = Select::new(
+                &format!("What should happen to {}?", file_name(&item.path)),
+                options.clone(),
+            )
+            .prompt()?;
+
+            match choice {
+                "Update from Modrinth" => match update_from_modrinth(&item.path, config) {
+                    Ok(filename) => {
+                        println!("✓ Updated {} to {}", file_name(&item.path), filename);
+                        break;
+                    }
+                    Err(e) => {
+                        println!("⚠ {}", e);
+                        options.retain(|o| *o != "Update from Modrinth");
+                    }
+                },
+                "Disable" => {
+                    let disabled = disable(&item.path)?;
+                    println!("✓ Disabled {}", file_name(&disabled));
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn disable(path: &Path) -> Result<PathBuf> {
+    let mut disabled = path.as_os_str().to_owned();
+    disabled.push(".disabled");
+    let disabled = PathBuf::from(disabled);
+    fs::rename(path, &disabled).context(format!("Failed to disable {:?}", path))?;
+    Ok(disabled)
+}
+
+fn update_from_modrinth(path: &Path, config: &ServerConfig) -> Result<String> {
+    let loader = config
+        .server_type
+        .modrinth_loader()
+        .ok_or_else(|| anyhow!("{} servers don't support mods or plugins.", config.server_type))?;
+
+    let current = modrinth::get_version_from_hash(&checksum::hash_file(path, Algorithm::Sha1)?)?
+        .ok_or_else(|| anyhow!("{} isn't a known Modrinth file", file_name(path)))?;
+
+    let versions =
+        modrinth::get_project_versions(&current.project_id, loader, &config.version)?;
+    let latest = versions.first().ok_or_else(|| {
+        anyhow!(
+            "No version of {} is available for {} {}",
+            file_name(path),
+            loader,
+            config.version
+        )
+    })?;
+
+    let file = latest
+        .files
+        .iter()
+        .find(|f| f.primary)
+        .or_else(|| latest.files.first())
+        .ok_or_else(|| anyhow!("No downloadable file found for this version"))?;
+
+    let dest_dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid path {:?}", path))?;
+    println!("↓ Downloading {}...", file.filename);
+    modrinth::download_file(file, &dest_dir.join(&file.filename))?;
+
+    if file.filename != file_name(path) {
+        fs::remove_file(path)?;
+    }
+
+    if let (Some(server_dir), Some(hash)) = (dest_dir.parent(), file.checksum()) {
+        let subdir = dest_dir.file_name().unwrap_or_default();
+        checksum::record(server_dir, &Path::new(subdir).join(&file.filename), &hash)?;
+    }
+
+    Ok(file.filename.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{config, temp_dir};
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    fn write_jar(path: &Path, name: &str, contents: &str) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn fabric_loader_constraint() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let config = config(ServerType::Fabric, "1.20.4");
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        write_jar(
+            &dir.join("mods/new.jar"),
+            "fabric.mod.json",
+            r#"{ "id": "new", "depends": { "minecraft": "1.20.x", "fabricloader": ">=0.16.0" } }"#,
+        );
+        write_jar(
+            &dir.join("mods/ok.jar"),
+            "fabric.mod.json",
+            r#"{ "id": "ok", "depends": { "minecraft": "1.20.x", "fabricloader": ">=0.15.0" } }"#,
+        );
+
+        let incompatible = check_jars(dir, &config, Some("0.15.6"));
+        let upgraded = check_jars(dir, &config, Some("0.16.5"));
+
+        assert_eq!(incompatible.len(), 1);
+        assert!(incompatible[0].path.ends_with("new.jar"));
+        assert_eq!(incompatible[0].reason, "requires Fabric Loader >=0.16.0 (installed: 0.15.6)");
+        assert!(upgraded.is_empty());
+    }
+
+    #[test]
+    fn forge_loader_constraint() {
+        let config = config(ServerType::Forge, "1.20.1");
+        let meta = |loader: &str| JarMetadata {
+            kind: JarKind::Forge,
+            id: "jei".to_string(),
+            name: None,
+            version: None,
+            dependencies: Vec::new(),
+            minecraft: Some("[1.20.1,1.20.2)".to_string()),
+            loader: Some(loader.to_string()),
+        };
+        assert_eq!(declared_mismatch(&meta("[47,)"), &config, Some("47.2.0")), None);
+        assert_eq!(
+            declared_mismatch(&meta("[47.3,)"), &config, Some("47.2.0")).as_deref(),
+            Some("requires Forge [47.3,) (installed: 47.2.0)")
+        );
+        // Without a known loader build only the Minecraft version is checked
+        assert_eq!(declared_mismatch(&meta("[48,)"), &config, None), None);
+    }
+
+    #[test]
+    fn unreadable_jar_is_reported() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let config = config(ServerType::Fabric, "1.20.4");
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::write(dir.join("mods/broken.jar"), "not a zip").unwrap();
+        write_jar(
+            &dir.join("mods/old.jar"),
+            "fabric.mod.json",
+            r#"{ "id": "old", "depends": { "minecraft": "1.19.x" } }"#,
+        );
+
+        let incompatible = check_jars(dir, &config, None);
+
+        assert_eq!(incompatible.len(), 2);
+        assert!(incompatible[0].path.ends_with("broken.jar"));
+        assert!(incompatible[0].reason.starts_with("is unreadable ("));
+        assert!(incompatible[1].path.ends_with("old.jar"));
+    }
+}
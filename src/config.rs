@@ -1,15 +1,118 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
-use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Context, Result, anyhow};
+use crate::{overlay, properties};
+use crate::units::{self, MemorySize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub version: String,
     pub server_type: ServerType,
-    pub memory: String,
-    #[serde(default)]
+    pub memory: MemorySize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_memory: Option<MemorySize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<MemorySize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flags: Option<FlagPreset>,
+    // Replaced by `flags`; still read so older mcs.toml files keep Paper's flags
+    #[serde(default, skip_serializing)]
     pub recommended_flags: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jvm_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub server_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartPolicy>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    // Merged into server.properties by `mcs apply`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, toml::Value>,
+    // YAML files (bukkit.yml, config/paper-global.yml, ...) deep-merged by `mcs apply`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, toml::Table>,
+}
+
+// `restart = { on_crash = true, max_restarts = 5, window = "10m", delay = "5s" }`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestartPolicy {
+    #[serde(default = "RestartPolicy::default_on_crash")]
+    pub on_crash: bool,
+    #[serde(default = "RestartPolicy::default_max_restarts")]
+    pub max_restarts: u32,
+    #[serde(default = "RestartPolicy::default_window")]
+    pub window: String,
+    #[serde(default = "RestartPolicy::default_delay")]
+    pub delay: String,
+}
+
+impl RestartPolicy {
+    fn default_on_crash() -> bool {
+        true
+    }
+
+    fn default_max_restarts() -> u32 {
+        5
+    }
+
+    fn default_window() -> String {
+        "10m".to_string()
+    }
+
+    fn default_delay() -> String {
+        "5s".to_string()
+    }
+
+    pub fn window(&self) -> Result<Duration> {
+        units::parse_duration(&self.window).context("Invalid restart window in mcs.toml")
+    }
+
+    pub fn delay(&self) -> Result<Duration> {
+        units::parse_duration(&self.delay).context("Invalid restart delay in mcs.toml")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlagPreset {
+    Aikar,
+    PaperApi,
+    Zgc,
+    None,
+}
+
+impl std::fmt::Display for FlagPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlagPreset::Aikar => write!(f, "aikar"),
+            FlagPreset::PaperApi => write!(f, "paper-api"),
+            FlagPreset::Zgc => write!(f, "zgc"),
+            FlagPreset::None => write!(f, "none"),
+        }
+    }
+}
+
+impl std::str::FromStr for FlagPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aikar" => Ok(FlagPreset::Aikar),
+            "paper-api" => Ok(FlagPreset::PaperApi),
+            "zgc" => Ok(FlagPreset::Zgc),
+            "none" => Ok(FlagPreset::None),
+            _ => Err(format!(
+                "unknown flags preset '{}' (expected aikar, paper-api, zgc or none)",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -33,27 +136,122 @@ impl std::fmt::Display for ServerType {
     }
 }
 
+impl std::str::FromStr for ServerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "paper" => Ok(ServerType::Paper),
+            "vanilla" => Ok(ServerType::Vanilla),
+            "fabric" => Ok(ServerType::Fabric),
+            "spigot" => Ok(ServerType::Spigot),
+            "forge" => Ok(ServerType::Forge),
+            _ => Err(format!(
+                "unknown server type '{}' (expected paper, vanilla, fabric, spigot or forge)",
+                s
+            )),
+        }
+    }
+}
+
+impl ServerType {
+    pub fn content_dir(&self) -> Option<&'static str> {
+        match self {
+            ServerType::Paper | ServerType::Spigot => Some("plugins"),
+            ServerType::Fabric | ServerType::Forge => Some("mods"),
+            ServerType::Vanilla => None,
+        }
+    }
+
+    pub fn modrinth_loader(&self) -> Option<&'static str> {
+        match self {
+            ServerType::Paper => Some("paper"),
+            ServerType::Spigot => Some("spigot"),
+            ServerType::Fabric => Some("fabric"),
+            ServerType::Forge => Some("forge"),
+            ServerType::Vanilla => None,
+        }
+    }
+}
+
+// [env] keys go into `export K=...` and `set "K=V"` lines in the start scripts
+fn check_env(env: &BTreeMap<String, String>) -> Result<()> {
+    for key in env.keys() {
+        let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(anyhow!(
+                "Invalid [env] in mcs.toml: '{}' must be letters, digits and underscores, not starting with a digit",
+                key
+            ));
+        }
+    }
+    Ok(())
+}
+
 impl ServerConfig {
     pub fn new(
         version: String,
         server_type: ServerType,
-        memory: String,
-        recommended_flags: bool,
+        memory: MemorySize,
+        flags: FlagPreset,
     ) -> Self {
         Self {
             version,
             server_type,
             memory,
-            recommended_flags,
+            min_memory: None,
+            max_memory: None,
+            flags: Some(flags),
+            recommended_flags: false,
+            jvm_args: Vec::new(),
+            server_args: Vec::new(),
+            java_path: None,
+            restart: None,
+            env: BTreeMap::new(),
+            properties: BTreeMap::new(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn flag_preset(&self) -> FlagPreset {
+        match self.flags {
+            Some(flags) => flags,
+            None if self.recommended_flags => FlagPreset::PaperApi,
+            None => FlagPreset::None,
         }
     }
 
+    pub fn min_memory(&self) -> MemorySize {
+        self.min_memory.unwrap_or(self.memory)
+    }
+
+    pub fn max_memory(&self) -> MemorySize {
+        self.max_memory.unwrap_or(self.memory)
+    }
+
+    // The Java executable to launch the server and installers with
+    pub fn java(&self) -> String {
+        self.java_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "java".to_string())
+    }
+
     pub fn load(path: &Path) -> Result<Self> {
         let config_path = path.join("mcs.toml");
         let contents = fs::read_to_string(&config_path)
             .context(format!("Failed to read config file at {:?}", config_path))?;
-        let config: ServerConfig = toml::from_str(&contents)
+        let mut config: ServerConfig = toml::from_str(&contents)
             .context("Failed to parse config file")?;
+        config.flags = Some(config.flag_preset());
+        if let Some(restart) = &config.restart {
+            restart.window()?;
+            restart.delay()?;
+        }
+        check_env(&config.env)?;
+        properties::check_config(&config.properties)?;
+        overlay::check_config(&config.files)?;
         Ok(config)
     }
 
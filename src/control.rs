@@ -0,0 +1,299 @@
+use anyhow::Result;
+#[cfg(unix)]
+use anyhow::{Context, anyhow};
+use std::path::Path;
+use std::time::Duration;
+use crate::supervisor::Status;
+use crate::system;
+
+// The supervisor listens on a Unix socket in the server directory. A client sends one
+// request line: "status" is answered with a JSON line, "attach" turns the connection
+// into the server console, and "stop <secs>" streams the console until the server is gone.
+#[cfg(unix)]
+pub use unix::*;
+
+#[cfg(not(unix))]
+fn unsupported() -> anyhow::Error {
+    anyhow::anyhow!("Running servers in the background is only supported on Linux and macOS. Use 'mcs run' instead.")
+}
+
+#[cfg(not(unix))]
+pub fn start_detached(_path: &Path, _stop_timeout: &str) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(unix))]
+pub fn attach(_path: &Path) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(unix))]
+pub fn stop(_path: &Path, _timeout: Duration) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(unix))]
+pub fn status(_path: &Path) -> Result<Option<Status>> {
+    Err(unsupported())
+}
+
+// Whether the server in this directory is up: it's under an mcs supervisor, or its JVM
+// was started some other way (start.sh, a panel) from this directory. Unlike status
+// this also answers where background servers aren't supported.
+pub fn is_running(path: &Path) -> bool {
+    matches!(status(path), Ok(Some(_))) || system::java_running_in(path)
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+    use std::os::unix::process::CommandExt;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+    use tokio::net::unix::OwnedWriteHalf;
+    use tokio::sync::{broadcast, mpsc};
+    use tokio::task::JoinHandle;
+    use crate::metadata;
+    use crate::supervisor::{Input, Session};
+
+    pub const SOCKET: &str = ".mcs.sock";
+    pub const PID_FILE: &str = ".mcs.pid";
+    const SUPERVISOR_LOG: &str = "logs/supervisor.log";
+
+    fn not_running() -> anyhow::Error {
+        anyhow!("The server isn't running. Start it with 'mcs start --detach'.")
+    }
+
+    // Held by a running supervisor; removes the socket and pid file when it finishes
+    pub struct Control {
+        socket: PathBuf,
+        pid_file: PathBuf,
+        accept: JoinHandle<()>,
+        connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    }
+
+    impl Control {
+        // Waits (briefly) for attached consoles to receive the remaining output
+        pub async fn close(self) {
+            self.accept.abort();
+            let _ = fs::remove_file(&self.socket);
+            let _ = fs::remove_file(&self.pid_file);
+            let connections = std::mem::take(&mut *self.connections.lock().unwrap());
+            for connection in connections {
+                let _ = tokio::time::timeout(Duration::from_secs(2), connection).await;
+            }
+        }
+    }
+
+    impl Drop for Control {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.socket);
+            let _ = fs::remove_file(&self.pid_file);
+        }
+    }
+
+    pub fn listen(path: &Path, input: mpsc::Sender<Input>, session: Arc<Session>) -> Result<Control> {
+        let socket = path.join(SOCKET);
+        if UnixStream::connect(&socket).is_ok() {
+            return Err(anyhow!(
+                "The server is already running. Use 'mcs attach' to open its console or 'mcs stop' to stop it."
+            ));
+        }
+        // Left behind by a supervisor that was killed
+        let _ = fs::remove_file(&socket);
+
+        let listener = UnixListener::bind(&socket).context(format!("Failed to listen on {:?}", socket))?;
+        let pid_file = path.join(PID_FILE);
+        fs::write(&pid_file, format!("{}\n", std::process::id()))?;
+
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let accept = tokio::spawn({
+            let connections = connections.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let connection = tokio::spawn(serve(stream, input.clone(), session.clone()));
+                    let mut connections = connections.lock().unwrap();
+                    connections.retain(|c: &JoinHandle<()>| !c.is_finished());
+                    connections.push(connection);
+                }
+            }
+        });
+
+        Ok(Control {
+            socket,
+            pid_file,
+            accept,
+            connections,
+        })
+    }
+
+    async fn stream_output(writer: &mut OwnedWriteHalf, mut output: broadcast::Receiver<Arc<str>>) {
+        loop {
+            match output.recv().await {
+                Ok(line) => {
+                    if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn serve(stream: tokio::net::UnixStream, input: mpsc::Sender<Input>, session: Arc<Session>) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        let Ok(Some(request)) = lines.next_line().await else {
+            return;
+        };
+        let mut words = request.split_whitespace();
+
+        match words.next() {
+            Some("status") => {
+                if let Ok(json) = serde_json::to_string(&session.status()) {
+                    let _ = writer.write_all(format!("{}\n", json).as_bytes()).await;
+                }
+            }
+            Some("stop") => {
+                let timeout = words.next().and_then(|s| s.parse().ok()).map(Duration::from_secs);
+                let (_, output) = session.subscribe();
+                let _ = input.send(Input::Stop(timeout)).await;
+                stream_output(&mut writer, output).await;
+            }
+            Some("attach") => {
+                let (backlog, output) = session.subscribe();
+                for line in backlog {
+                    let _ = writer.write_all(format!("{}\n", line).as_bytes()).await;
+                }
+                let forward_input = async {
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if input.send(Input::Command(line)).await.is_err() {
+                            break;
+                        }
+                    }
+                };
+                tokio::select! {
+                    _ = stream_output(&mut writer, output) => {}
+                    _ = forward_input => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(path: &Path) -> Option<UnixStream> {
+        UnixStream::connect(path.join(SOCKET)).ok()
+    }
+
+    // None when no supervisor is running for this server
+    pub fn status(path: &Path) -> Result<Option<Status>> {
+        let Some(mut stream) = connect(path) else {
+            return Ok(None);
+        };
+        stream.write_all(b"status\n")?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let status = serde_json::from_str(&line).context("Invalid status from the server's supervisor")?;
+        Ok(Some(status))
+    }
+
+    pub fn attach(path: &Path) -> Result<()> {
+        let mut stream = connect(path).ok_or_else(not_running)?;
+        stream.write_all(b"attach\n")?;
+        println!("■ Attached to the server console. Press Ctrl+C or Ctrl+D to detach; the server keeps running.\n");
+
+        let mut writer = stream.try_clone()?;
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if writeln!(writer, "{}", line).is_err() {
+                    break;
+                }
+            }
+            // End of input detaches
+            let _ = writer.shutdown(Shutdown::Both);
+        });
+
+        for line in BufReader::new(stream).lines() {
+            println!("{}", line?);
+        }
+        Ok(())
+    }
+
+    pub fn stop(path: &Path, timeout: Duration) -> Result<()> {
+        let Some(mut stream) = connect(path) else {
+            println!("■ The server isn't running");
+            return Ok(());
+        };
+        writeln!(stream, "stop {}", timeout.as_secs())?;
+        for line in BufReader::new(stream).lines() {
+            println!("{}", line?);
+        }
+        Ok(())
+    }
+
+    // Starts a supervisor (`mcs supervise`) in a new session, so it has no controlling
+    // terminal and survives the terminal closing, and waits until the server process is up
+    pub fn start_detached(path: &Path, stop_timeout: &str) -> Result<()> {
+        if connect(path).is_some() {
+            return Err(anyhow!(
+                "The server is already running. Use 'mcs attach' to open its console or 'mcs stop' to stop it."
+            ));
+        }
+
+        fs::create_dir_all(path.join("logs"))?;
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.join(SUPERVISOR_LOG))?;
+
+        let mut command = Command::new(std::env::current_exe()?);
+        if metadata::is_offline() {
+            command.arg("--offline");
+        }
+        command
+            .args(["supervise", "--stop-timeout", stop_timeout])
+            .current_dir(path)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        // SAFETY: setsid is async-signal-safe and touches no memory of this process
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = command.spawn().context("Failed to start the supervisor")?;
+
+        // Fetching Paper's recommended flags can take a moment before the JVM starts
+        let deadline = Instant::now() + Duration::from_secs(60);
+        loop {
+            if let Some(status) = status(path)?
+                && let Some(server_pid) = status.server_pid
+            {
+                println!("✓ Server started in the background (PID {})", server_pid);
+                println!("→ Use 'mcs attach' to open its console and 'mcs stop' to stop it");
+                return Ok(());
+            }
+            if let Some(exit) = child.try_wait()? {
+                return Err(anyhow!("The server failed to start ({}). See {}.", exit, SUPERVISOR_LOG));
+            }
+            if Instant::now() > deadline {
+                return Err(anyhow!("The server didn't start within a minute. See {}.", SUPERVISOR_LOG));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
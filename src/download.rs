@@ -0,0 +1,141 @@
+use anyhow::{Context, Result, anyhow};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread;
+use crate::checksum::{self, Checksum};
+use crate::http;
+
+// Tries per download when the connection drops mid-transfer
+const MAX_ATTEMPTS: u32 = 3;
+
+// Every progress bar is drawn through one MultiProgress so parallel downloads
+// stack their bars instead of overwriting each other's lines
+static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+fn progress() -> &'static MultiProgress {
+    PROGRESS.get_or_init(MultiProgress::new)
+}
+
+// Prints a line above any progress bars that are currently drawn
+pub fn status(message: impl std::fmt::Display) {
+    progress().suspend(|| println!("{}", message));
+}
+
+pub fn temp_path(dest: &Path) -> PathBuf {
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    dest.with_file_name(format!(".{}.part", name))
+}
+
+fn progress_bar(len: Option<u64>, label: &str) -> ProgressBar {
+    let bar = match len {
+        Some(len) => {
+            let bar = ProgressBar::new(len);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "  {msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec} {eta}",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+            );
+            bar
+        }
+        None => {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::with_template("  {msg} {spinner} {bytes} {bytes_per_sec}")
+                    .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            );
+            bar
+        }
+    };
+    bar.set_message(label.to_string());
+    progress().add(bar)
+}
+
+// Streams `url` into a temp file next to `dest` and only renames it into place once
+// the transfer finished and the checksum (when known) matched. Nothing is left
+// behind on failure, so an existing `dest` is never replaced by a partial file.
+pub fn download(client: &Client, url: &str, dest: &Path, expected: Option<&Checksum>) -> Result<()> {
+    let temp = temp_path(dest);
+    let mut attempt = 0;
+
+    loop {
+        let result = download_to(client, url, &temp, dest, expected);
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+
+        // A connection dropped mid-transfer is retried from the start
+        match result {
+            Err(e) if e.downcast_ref::<Interrupted>().is_some() && attempt + 1 < MAX_ATTEMPTS => {
+                let delay = http::backoff(attempt);
+                status(format!("⚠ {}, retrying in {:.1}s...", e, delay.as_secs_f32()));
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Interrupted(String);
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Download of {} was interrupted", self.0)
+    }
+}
+
+fn download_to(
+    client: &Client,
+    url: &str,
+    temp: &Path,
+    dest: &Path,
+    expected: Option<&Checksum>,
+) -> Result<()> {
+    let mut response =
+        http::send(client.get(url)).context(format!("Failed to download {}", url))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to download {}: HTTP {}", url, response.status()));
+    }
+
+    let label = dest.file_name().unwrap_or_default().to_string_lossy();
+    let bar = progress_bar(response.content_length(), &label);
+
+    let file = File::create(temp).context(format!("Failed to create {:?}", temp))?;
+    let mut writer = BufWriter::new(file);
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let n = match response.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                bar.abandon();
+                return Err(anyhow!(e).context(Interrupted(label.into_owned())));
+            }
+        };
+        writer.write_all(&buf[..n])?;
+        bar.inc(n as u64);
+    }
+
+    writer.flush()?;
+    writer
+        .into_inner()
+        .map_err(|e| anyhow!(e.into_error()))?
+        .sync_all()?;
+    bar.finish_and_clear();
+
+    if let Some(expected) = expected {
+        checksum::verify_file(temp, expected)?;
+    }
+
+    fs::rename(temp, dest).context(format!("Failed to move download into {:?}", dest))?;
+    Ok(())
+}
@@ -1,24 +1,18 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use reqwest::blocking::Client;
-use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
+use crate::checksum::{self, Algorithm, Checksum};
 use crate::config::ServerConfig;
+use crate::http;
+use crate::cache;
+use crate::metadata;
 
 pub fn get_available_versions() -> Result<Vec<String>> {
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
+    let client = http::client()?;
 
-    let response = client
-        .get("https://meta.fabricmc.net/v2/versions/game")
-        .send()?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Fabric versions from API"));
-    }
-
-    let data: Value = response.json()?;
+    let data = metadata::get_json(&client, "https://meta.fabricmc.net/v2/versions/game")?
+        .ok_or_else(|| anyhow!("Failed to fetch Fabric versions from API"))?;
     let versions = data
         .as_array()
         .ok_or_else(|| anyhow!("Failed to parse versions from API"))?
@@ -30,23 +24,10 @@ pub fn get_available_versions() -> Result<Vec<String>> {
     Ok(versions)
 }
 
-pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
-    println!("\n↓ Downloading Fabric server...");
-
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
-
-    println!("■ Fetching Fabric loader version...");
-    let loader_response = client
-        .get("https://meta.fabricmc.net/v2/versions/loader")
-        .send()?;
-
-    if !loader_response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Fabric loader versions"));
-    }
-
-    let loaders: Value = loader_response.json()?;
+// The loader build setup_server installs: always the newest one
+pub fn latest_loader(client: &Client) -> Result<String> {
+    let loaders = metadata::get_json(client, "https://meta.fabricmc.net/v2/versions/loader")?
+        .ok_or_else(|| anyhow!("Failed to fetch Fabric loader versions"))?;
     let loader_version = loaders
         .as_array()
         .ok_or_else(|| anyhow!("Failed to parse loader versions"))?
@@ -54,18 +35,21 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
         .ok_or_else(|| anyhow!("No loader versions found"))?["version"]
         .as_str()
         .ok_or_else(|| anyhow!("Failed to get loader version"))?;
+    Ok(loader_version.to_string())
+}
 
-    println!("■ Using Fabric Loader {}", loader_version);
+pub fn setup_server(path: &Path, config: &ServerConfig) -> Result<()> {
+    println!("\n↓ Downloading Fabric server...");
 
-    let installer_response = client
-        .get("https://meta.fabricmc.net/v2/versions/installer")
-        .send()?;
+    let client = http::client()?;
+
+    println!("■ Fetching Fabric loader version...");
+    let loader_version = latest_loader(&client)?;
 
-    if !installer_response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Fabric installer versions"));
-    }
+    println!("■ Using Fabric Loader {}", loader_version);
 
-    let installers: Value = installer_response.json()?;
+    let installers = metadata::get_json(&client, "https://meta.fabricmc.net/v2/versions/installer")?
+        .ok_or_else(|| anyhow!("Failed to fetch Fabric installer versions"))?;
     let installer_version = installers
         .as_array()
         .ok_or_else(|| anyhow!("Failed to parse installer versions"))?
@@ -82,19 +66,15 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
     );
 
     println!("■ Downloading Fabric server for Minecraft {}...", config.version);
-    let jar_response = client.get(&download_url).send()?;
-
-    if !jar_response.status().is_success() {
-        return Err(anyhow!(
-            "Version {} may not be available for Fabric. Please check the version number.",
-            config.version
-        ));
-    }
-
-    let jar_bytes = jar_response.bytes()?;
-
     let server_jar_path = path.join("server.jar");
-    fs::write(&server_jar_path, jar_bytes)?;
+    cache::fetch(&client, &download_url, &server_jar_path, None).context(format!(
+        "Version {} may not be available for Fabric. Please check the version number.",
+        config.version
+    ))?;
+    // Fabric meta publishes no hashes, so record what was received to catch later changes
+    let sha256 = checksum::hash_file(&server_jar_path, Algorithm::Sha256)?;
+    let received = Checksum::new(Algorithm::Sha256, &sha256);
+    checksum::record(path, Path::new("server.jar"), &received)?;
     fs::create_dir_all(path.join("mods"))?;
 
     println!("✓ Downloaded server.jar");
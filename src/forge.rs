@@ -1,24 +1,21 @@
 use anyhow::{Result, anyhow};
 use reqwest::blocking::Client;
-use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
+use crate::checksum::{Algorithm, Checksum};
 use crate::config::ServerConfig;
+use crate::http;
+use crate::cache;
+use crate::metadata;
 
 pub fn get_available_versions() -> Result<Vec<String>> {
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
+    let client = http::client()?;
 
-    let response = client
-        .get("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json")
-        .send()?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Forge versions from API"));
-    }
-
-    let data: Value = response.json()?;
+    let data = metadata::get_json(
+        &client,
+        "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json",
+    )?
+    .ok_or_else(|| anyhow!("Failed to fetch Forge versions from API"))?;
     let promos = data["promos"]
         .as_object()
         .ok_or_else(|| anyhow!("Failed to parse Forge promotions"))?;
@@ -45,35 +42,36 @@ pub fn get_available_versions() -> Result<Vec<String>> {
     Ok(versions)
 }
 
-pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
-    println!("\n↓ Downloading Forge server...");
-
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
-
-    println!("■ Fetching Forge build information for {}...", config.version);
-    let promos_response = client
-        .get("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json")
-        .send()?;
-
-    if !promos_response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Forge promotions"));
-    }
-
-    let promos: Value = promos_response.json()?;
+// The Forge build setup_server installs for a Minecraft version: the recommended
+// one, or the latest when none is recommended
+pub fn build_for(client: &Client, version: &str) -> Result<String> {
+    let promos = metadata::get_json(
+        client,
+        "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json",
+    )?
+    .ok_or_else(|| anyhow!("Failed to fetch Forge promotions"))?;
     let promos_obj = promos["promos"]
         .as_object()
         .ok_or_else(|| anyhow!("Failed to parse Forge promotions"))?;
 
-    let recommended_key = format!("{}-recommended", config.version);
-    let latest_key = format!("{}-latest", config.version);
+    let recommended_key = format!("{}-recommended", version);
+    let latest_key = format!("{}-latest", version);
 
     let forge_build = promos_obj
         .get(&recommended_key)
         .or_else(|| promos_obj.get(&latest_key))
         .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("No Forge build found for Minecraft version {}", config.version))?;
+        .ok_or_else(|| anyhow!("No Forge build found for Minecraft version {}", version))?;
+    Ok(forge_build.to_string())
+}
+
+pub fn setup_server(path: &Path, config: &ServerConfig) -> Result<()> {
+    println!("\n↓ Downloading Forge server...");
+
+    let client = http::client()?;
+
+    println!("■ Fetching Forge build information for {}...", config.version);
+    let forge_build = build_for(&client, &config.version)?;
 
     let forge_version = format!("{}-{}", config.version, forge_build);
     println!("■ Using Forge {}", forge_version);
@@ -83,24 +81,23 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
         forge_version, forge_version
     );
 
-    println!("■ Downloading Forge installer...");
-    let installer_response = client.get(&installer_url).send()?;
-
-    if !installer_response.status().is_success() {
-        return Err(anyhow!(
+    // Maven publishes a .sha1 next to every artifact
+    let sha1 = metadata::get_text(&client, &format!("{}.sha1", installer_url))?.ok_or_else(|| {
+        anyhow!(
             "Failed to download Forge installer for version {}. The version may not be available.",
             forge_version
-        ));
-    }
+        )
+    })?;
+    let expected = Checksum::new(Algorithm::Sha1, sha1.split_whitespace().next().unwrap_or_default());
 
-    let installer_bytes = installer_response.bytes()?;
+    println!("■ Downloading Forge installer...");
     let installer_path = path.join("forge-installer.jar");
-    fs::write(&installer_path, installer_bytes)?;
+    cache::fetch(&client, &installer_url, &installer_path, Some(&expected))?;
 
-    println!("✓ Downloaded Forge installer");
+    println!("✓ Downloaded Forge installer (sha1 verified)");
     println!("\n■ Installing Forge server (this may take a moment)...");
 
-    let output = std::process::Command::new("java")
+    let output = std::process::Command::new(config.java())
         .arg("-jar")
         .arg("forge-installer.jar")
         .arg("--installServer")
@@ -134,8 +131,7 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
 
             if !found_jar {
                 if path.join("run.sh").exists() || path.join("run.bat").exists() {
-                    println!("⚠ Note: This Forge version uses run.sh/run.bat for starting.");
-                    println!("  The generated start scripts will work, but you can also use the Forge scripts.");
+                    println!("■ This Forge version starts from argument files in libraries/");
                 } else {
                     return Err(anyhow!("Failed to find Forge server jar after installation"));
                 }
@@ -149,7 +145,7 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
             println!("✓ Created mods directory");
         }
         Err(e) => {
-            return Err(anyhow!("Failed to run Forge installer. Make sure Java is installed and in PATH: {}", e));
+            return Err(anyhow!("Failed to run Forge installer with {}: {}", config.java(), e));
         }
     }
 
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const CONFIG_FILE: &str = "config.toml";
+
+// Every upstream mcs talks to, by the name used in [endpoints] and the base URL it
+// replaces. Overrides apply to any request starting with that base, including URLs
+// returned by the APIs themselves (Mojang and Paper download links, Modrinth's CDN).
+pub const ENDPOINTS: &[(&str, &str)] = &[
+    ("modrinth_api", "https://api.modrinth.com"),
+    ("modrinth_cdn", "https://cdn.modrinth.com"),
+    ("paper_api", "https://fill.papermc.io"),
+    ("paper_downloads", "https://fill-data.papermc.io"),
+    ("mojang_meta", "https://launchermeta.mojang.com"),
+    ("mojang_piston_meta", "https://piston-meta.mojang.com"),
+    ("mojang_downloads", "https://piston-data.mojang.com"),
+    ("mojang_launcher", "https://launcher.mojang.com"),
+    ("fabric_meta", "https://meta.fabricmc.net"),
+    ("forge_maven", "https://maven.minecraftforge.net"),
+    ("forge_files", "https://files.minecraftforge.net"),
+    ("spigot_hub", "https://hub.spigotmc.org"),
+    ("adoptium_api", "https://api.adoptium.net"),
+    ("adoptium_downloads", "https://github.com/adoptium"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GlobalConfig {
+    #[serde(default)]
+    pub endpoints: BTreeMap<String, String>,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyConfig {
+    pub http: Option<String>,
+    pub https: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+static CONFIG: OnceLock<GlobalConfig> = OnceLock::new();
+
+// Root of everything mcs keeps outside a server directory. MCS_HOME overrides ~/.mcs.
+pub fn mcs_home() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os("MCS_HOME").filter(|h| !h.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+
+    std::env::home_dir()
+        .map(|home| home.join(".mcs"))
+        .ok_or_else(|| anyhow!("Could not determine your home directory. Set MCS_HOME instead."))
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+// ~/.mcs/config.toml, with MCS_ENDPOINT_<NAME> and MCS_HTTP(S)_PROXY / MCS_NO_PROXY
+// taking precedence over the file
+pub fn config() -> Result<&'static GlobalConfig> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+
+    let path = mcs_home()?.join(CONFIG_FILE);
+    let mut config: GlobalConfig = if path.exists() {
+        let contents =
+            fs::read_to_string(&path).context(format!("Failed to read {:?}", path))?;
+        toml::from_str(&contents).context(format!("Failed to parse {:?}", path))?
+    } else {
+        GlobalConfig::default()
+    };
+
+    for name in config.endpoints.keys() {
+        if !ENDPOINTS.iter().any(|(known, _)| known == name) {
+            let known: Vec<_> = ENDPOINTS.iter().map(|(known, _)| *known).collect();
+            return Err(anyhow!(
+                "Unknown endpoint '{}' in {:?}. Known endpoints: {}",
+                name,
+                path,
+                known.join(", ")
+            ));
+        }
+    }
+
+    for (name, _) in ENDPOINTS {
+        if let Some(url) = env(&format!("MCS_ENDPOINT_{}", name.to_ascii_uppercase())) {
+            config.endpoints.insert(name.to_string(), url);
+        }
+    }
+    for (name, value) in config.endpoints.iter_mut() {
+        *value = value.trim().trim_end_matches('/').to_string();
+        Url::parse(value).context(format!("Invalid URL for endpoint '{}'", name))?;
+    }
+
+    if let Some(http) = env("MCS_HTTP_PROXY") {
+        config.proxy.http = Some(http);
+    }
+    if let Some(https) = env("MCS_HTTPS_PROXY") {
+        config.proxy.https = Some(https);
+    }
+    if let Some(no_proxy) = env("MCS_NO_PROXY") {
+        config.proxy.no_proxy = Some(no_proxy);
+    }
+
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+// Points a request at its configured mirror, if there is one
+pub fn resolve_url(url: &Url) -> Result<Url> {
+    let endpoints = &config()?.endpoints;
+    let url_str = url.as_str();
+
+    for (name, default) in ENDPOINTS {
+        let Some(mirror) = endpoints.get(*name) else {
+            continue;
+        };
+        let Some(rest) = url_str.strip_prefix(default) else {
+            continue;
+        };
+        if rest.is_empty() || rest.starts_with(['/', '?']) {
+            return Url::parse(&format!("{}{}", mirror, rest))
+                .context(format!("Invalid URL for endpoint '{}'", name));
+        }
+    }
+
+    Ok(url.clone())
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use reqwest::{NoProxy, Proxy};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::global;
+
+pub const USER_AGENT: &str = concat!("mcs/", env!("CARGO_PKG_VERSION"), " (github.com/dxkyy/mcs)");
+
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+// Upper bound for waiting on a rate limit before giving up on the request
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(120);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+// Per-host instant before which no new request should be sent
+static NOT_BEFORE: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+// The one HTTP client every module shares. Cheap to clone.
+pub fn client() -> Result<Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(10))
+        // Applies to each read as well, so large downloads aren't cut off
+        .timeout(Duration::from_secs(30));
+
+    // Without a configured proxy, reqwest still honors HTTP_PROXY/HTTPS_PROXY/NO_PROXY
+    let proxy = &global::config()?.proxy;
+    let no_proxy = proxy.no_proxy.as_deref().and_then(NoProxy::from_string);
+    if let Some(url) = &proxy.http {
+        builder = builder.proxy(
+            Proxy::http(url)
+                .context(format!("Invalid HTTP proxy '{}'", url))?
+                .no_proxy(no_proxy.clone()),
+        );
+    }
+    if let Some(url) = &proxy.https {
+        builder = builder.proxy(
+            Proxy::https(url)
+                .context(format!("Invalid HTTPS proxy '{}'", url))?
+                .no_proxy(no_proxy),
+        );
+    }
+
+    let client = builder.build().context("Failed to build HTTP client")?;
+
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+pub fn backoff(attempt: u32) -> Duration {
+    BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_DELAY)
+}
+
+fn wait_for_host(host: &str) {
+    let until = NOT_BEFORE
+        .lock()
+        .ok()
+        .and_then(|map| map.as_ref()?.get(host).copied());
+    if let Some(until) = until {
+        let now = Instant::now();
+        if until > now {
+            thread::sleep(until - now);
+        }
+    }
+}
+
+fn pause_host(host: &str, wait: Duration) {
+    if let Ok(mut map) = NOT_BEFORE.lock() {
+        map.get_or_insert_with(HashMap::new)
+            .insert(host.to_string(), Instant::now() + wait.min(MAX_RATE_LIMIT_WAIT));
+    }
+}
+
+fn header_secs(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+// Modrinth reports its budget in X-Ratelimit-Remaining and the seconds until it
+// refills in X-Ratelimit-Reset; other APIs only send Retry-After with a 429.
+fn rate_limit_wait(headers: &HeaderMap, limited: bool) -> Option<Duration> {
+    let reset = header_secs(headers, "x-ratelimit-reset")
+        .or_else(|| header_secs(headers, "retry-after"));
+
+    if limited {
+        return Some(Duration::from_secs(reset.unwrap_or(1).max(1)));
+    }
+
+    match header_secs(headers, "x-ratelimit-remaining") {
+        Some(0) => reset.map(|r| Duration::from_secs(r.max(1))),
+        _ => None,
+    }
+}
+
+// Sends a request to its configured mirror, retrying connection errors, timeouts, 5xx
+// and 429 responses with exponential backoff. Any other response (including 4xx) is
+// returned to the caller.
+pub fn send(request: RequestBuilder) -> Result<Response> {
+    let (client, request) = request.build_split();
+    let mut request = request.context("Invalid request")?;
+    *request.url_mut() = global::resolve_url(request.url())?;
+
+    let host = request.url().host_str().unwrap_or_default().to_string();
+    let mut attempt = 0;
+
+    loop {
+        wait_for_host(&host);
+
+        let this_try = request
+            .try_clone()
+            .ok_or_else(|| anyhow!("Request body can't be retried"))?;
+
+        let error = match client.execute(this_try) {
+            Ok(response) => {
+                let status = response.status();
+                let limited = status == StatusCode::TOO_MANY_REQUESTS;
+
+                if let Some(wait) = rate_limit_wait(response.headers(), limited) {
+                    if limited {
+                        println!(
+                            "⚠ Rate limited by {}, waiting {}s...",
+                            host,
+                            wait.as_secs()
+                        );
+                    }
+                    pause_host(&host, wait);
+                }
+
+                if !limited && !status.is_server_error() {
+                    return Ok(response);
+                }
+                if attempt + 1 >= MAX_ATTEMPTS {
+                    return Ok(response);
+                }
+                if limited {
+                    attempt += 1;
+                    continue;
+                }
+                anyhow!("HTTP {}", status)
+            }
+            Err(e) if (e.is_connect() || e.is_timeout() || e.is_request())
+                && attempt + 1 < MAX_ATTEMPTS =>
+            {
+                anyhow!(e)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let delay = backoff(attempt);
+        println!(
+            "⚠ Request to {} failed ({}), retrying in {:.1}s...",
+            host,
+            error,
+            delay.as_secs_f32()
+        );
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
@@ -0,0 +1,525 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value as JsonValue;
+use serde_norway::Value as YamlValue;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JarKind {
+    Bukkit,
+    PaperPlugin,
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+}
+
+impl std::fmt::Display for JarKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JarKind::Bukkit => write!(f, "Bukkit plugin"),
+            JarKind::PaperPlugin => write!(f, "Paper plugin"),
+            JarKind::Fabric => write!(f, "Fabric mod"),
+            JarKind::Quilt => write!(f, "Quilt mod"),
+            JarKind::Forge => write!(f, "Forge mod"),
+            JarKind::NeoForge => write!(f, "NeoForge mod"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JarDependency {
+    pub id: String,
+    pub version: Option<String>,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct JarMetadata {
+    pub kind: JarKind,
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: Vec<JarDependency>,
+    // Declared Minecraft version constraint, in the loader's own range syntax
+    pub minecraft: Option<String>,
+    // Declared loader version constraint (fabricloader, quilt_loader, forge, ...)
+    pub loader: Option<String>,
+}
+
+// Metadata files in the order they're checked; the first one present wins
+const METADATA_FILES: [(&str, JarKind); 6] = [
+    ("fabric.mod.json", JarKind::Fabric),
+    ("quilt.mod.json", JarKind::Quilt),
+    ("META-INF/neoforge.mods.toml", JarKind::NeoForge),
+    ("META-INF/mods.toml", JarKind::Forge),
+    ("paper-plugin.yml", JarKind::PaperPlugin),
+    ("plugin.yml", JarKind::Bukkit),
+];
+
+pub fn inspect(path: &Path) -> Result<Option<JarMetadata>> {
+    let file = File::open(path).context(format!("Failed to open {:?}", path))?;
+    let mut archive =
+        ZipArchive::new(file).context(format!("{:?} is not a valid jar file", path))?;
+
+    for (name, kind) in METADATA_FILES {
+        let Some(contents) = read_entry(&mut archive, name)? else {
+            continue;
+        };
+
+        let metadata = match kind {
+            JarKind::Fabric => parse_fabric(&contents),
+            JarKind::Quilt => parse_quilt(&contents),
+            JarKind::Forge | JarKind::NeoForge => {
+                let manifest = read_entry(&mut archive, "META-INF/MANIFEST.MF")?;
+                parse_mods_toml(&contents, kind, manifest.as_deref())
+            }
+            JarKind::Bukkit | JarKind::PaperPlugin => parse_plugin_yml(&contents, kind),
+        }
+        .context(format!("Failed to parse {} in {:?}", name, path))?;
+
+        return Ok(Some(metadata));
+    }
+
+    Ok(None)
+}
+
+// A jar's file name for messages
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<String>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn parse_fabric(contents: &str) -> Result<JarMetadata> {
+    let data: JsonValue = serde_json::from_str(contents)?;
+
+    let mut minecraft = None;
+    let mut loader = None;
+    let mut dependencies = Vec::new();
+
+    for (key, required) in [("depends", true), ("recommends", false)] {
+        let Some(deps) = data[key].as_object() else {
+            continue;
+        };
+        for (id, constraint) in deps {
+            match id.as_str() {
+                "minecraft" if required => minecraft = json_range(constraint),
+                "fabricloader" if required => loader = json_range(constraint),
+                "java" => {}
+                _ => dependencies.push(JarDependency {
+                    id: id.clone(),
+                    version: json_range(constraint),
+                    required,
+                }),
+            }
+        }
+    }
+
+    Ok(JarMetadata {
+        kind: JarKind::Fabric,
+        id: json_string(&data["id"])?,
+        name: data["name"].as_str().map(String::from),
+        version: data["version"].as_str().map(String::from),
+        dependencies,
+        minecraft,
+        loader,
+    })
+}
+
+fn parse_quilt(contents: &str) -> Result<JarMetadata> {
+    let data: JsonValue = serde_json::from_str(contents)?;
+    let loader_section = &data["quilt_loader"];
+
+    let mut minecraft = None;
+    let mut loader = None;
+    let mut dependencies = Vec::new();
+
+    for dep in loader_section["depends"].as_array().into_iter().flatten() {
+        // Entries are either a bare id or an object with id/versions/optional
+        let (id, versions, optional) = match dep {
+            JsonValue::String(id) => (id.clone(), None, false),
+            JsonValue::Object(_) => (
+                json_string(&dep["id"])?,
+                json_range(&dep["versions"]),
+                dep["optional"].as_bool().unwrap_or(false),
+            ),
+            _ => continue,
+        };
+
+        match id.as_str() {
+            "minecraft" => minecraft = versions,
+            "quilt_loader" => loader = versions,
+            "java" => {}
+            _ => dependencies.push(JarDependency {
+                id,
+                version: versions,
+                required: !optional,
+            }),
+        }
+    }
+
+    Ok(JarMetadata {
+        kind: JarKind::Quilt,
+        id: json_string(&loader_section["id"])?,
+        name: loader_section["metadata"]["name"].as_str().map(String::from),
+        version: loader_section["version"].as_str().map(String::from),
+        dependencies,
+        minecraft,
+        loader,
+    })
+}
+
+fn parse_mods_toml(contents: &str, kind: JarKind, manifest: Option<&str>) -> Result<JarMetadata> {
+    let data: toml::Table = toml::from_str(contents)?;
+
+    let first_mod = data
+        .get("mods")
+        .and_then(|m| m.as_array())
+        .and_then(|m| m.first())
+        .ok_or_else(|| anyhow!("No [[mods]] entry found"))?;
+
+    let id = first_mod
+        .get("modId")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing modId"))?
+        .to_string();
+
+    // Forge jars commonly use ${file.jarVersion}, which resolves from the manifest
+    let version = first_mod
+        .get("version")
+        .and_then(|v| v.as_str())
+        .and_then(|v| {
+            if v == "${file.jarVersion}" {
+                manifest.and_then(|m| manifest_attribute(m, "Implementation-Version"))
+            } else {
+                Some(v.to_string())
+            }
+        });
+
+    let loader_id = if kind == JarKind::NeoForge { "neoforge" } else { "forge" };
+    let mut minecraft = None;
+    let mut loader = None;
+    let mut dependencies = Vec::new();
+
+    let deps = data
+        .get("dependencies")
+        .and_then(|d| d.get(&id))
+        .and_then(|d| d.as_array());
+
+    for dep in deps.into_iter().flatten() {
+        let Some(dep_id) = dep.get("modId").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let range = dep
+            .get("versionRange")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        // Older files use `mandatory`, newer ones `type = "required"`
+        let required = match dep.get("type").and_then(|v| v.as_str()) {
+            Some(t) => t.eq_ignore_ascii_case("required"),
+            None => dep.get("mandatory").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+
+        match dep_id {
+            "minecraft" => minecraft = range,
+            d if d == loader_id => loader = range,
+            _ => dependencies.push(JarDependency {
+                id: dep_id.to_string(),
+                version: range,
+                required,
+            }),
+        }
+    }
+
+    if loader.is_none() {
+        loader = data
+            .get("loaderVersion")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+    }
+
+    Ok(JarMetadata {
+        kind,
+        id,
+        name: first_mod
+            .get("displayName")
+            .and_then(|v| v.as_str())
+            .map(String::from),
+        version,
+        dependencies,
+        minecraft,
+        loader,
+    })
+}
+
+fn parse_plugin_yml(contents: &str, kind: JarKind) -> Result<JarMetadata> {
+    let data: YamlValue = serde_norway::from_str(contents)?;
+
+    let name = yaml_string(&data["name"])
+        .ok_or_else(|| anyhow!("Missing plugin name"))?;
+
+    let mut dependencies = Vec::new();
+
+    if kind == JarKind::PaperPlugin && data["dependencies"].is_mapping() {
+        // paper-plugin.yml: dependencies.{bootstrap,server}.<Name>.required
+        for section in ["bootstrap", "server"] {
+            let Some(deps) = data["dependencies"][section].as_mapping() else {
+                continue;
+            };
+            for (dep_name, dep) in deps {
+                let Some(dep_name) = yaml_string(dep_name) else {
+                    continue;
+                };
+                if dependencies.iter().any(|d: &JarDependency| d.id == dep_name) {
+                    continue;
+                }
+                dependencies.push(JarDependency {
+                    id: dep_name,
+                    version: None,
+                    required: dep["required"].as_bool().unwrap_or(true),
+                });
+            }
+        }
+    } else {
+        for (key, required) in [("depend", true), ("softdepend", false)] {
+            for dep in data[key].as_sequence().into_iter().flatten() {
+                if let Some(id) = yaml_string(dep) {
+                    dependencies.push(JarDependency {
+                        id,
+                        version: None,
+                        required,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(JarMetadata {
+        kind,
+        id: name.clone(),
+        name: Some(name),
+        version: raw_scalar(contents, "version").or_else(|| yaml_string(&data["version"])),
+        dependencies,
+        // api-version is the lowest Minecraft version the plugin targets
+        minecraft: raw_scalar(contents, "api-version")
+            .or_else(|| yaml_string(&data["api-version"]))
+            .map(|v| format!(">={}", v)),
+        loader: None,
+    })
+}
+
+fn manifest_attribute(manifest: &str, key: &str) -> Option<String> {
+    manifest.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k.trim() == key).then(|| v.trim().to_string())
+    })
+}
+
+fn json_string(value: &JsonValue) -> Result<String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Missing mod id"))
+}
+
+// Fabric and Quilt allow either a single predicate or an array of alternatives
+fn json_range(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|i| i.as_str())
+                .collect::<Vec<_>>()
+                .join(" || "),
+        ),
+        _ => None,
+    }
+}
+
+// Reads a top-level `key: value` line verbatim, since YAML turns `1.20` into 1.2
+fn raw_scalar(contents: &str, key: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        if k != key {
+            return None;
+        }
+        let v = v.split(" #").next()?.trim().trim_matches(|c| c == '"' || c == '\'');
+        (!v.is_empty()).then(|| v.to_string())
+    })
+}
+
+// Remaining YAML scalars like `version: 1.0` or `api-version: 1.20` parse as numbers
+fn yaml_string(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::String(s) => Some(s.clone()),
+        YamlValue::Number(n) => Some(n.to_string()),
+        YamlValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn fabric_mod_json() {
+        let meta = parse_fabric(
+            r#"{
+                "id": "sodium",
+                "name": "Sodium",
+                "version": "0.5.8",
+                "depends": {
+                    "minecraft": ["1.20.3", "1.20.4"],
+                    "fabricloader": ">=0.15.0",
+                    "java": ">=17",
+                    "fabric-api": "*"
+                },
+                "recommends": { "modmenu": ">=9" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(meta.kind, JarKind::Fabric);
+        assert_eq!(meta.id, "sodium");
+        assert_eq!(meta.name.as_deref(), Some("Sodium"));
+        assert_eq!(meta.version.as_deref(), Some("0.5.8"));
+        assert_eq!(meta.minecraft.as_deref(), Some("1.20.3 || 1.20.4"));
+        assert_eq!(meta.loader.as_deref(), Some(">=0.15.0"));
+        assert_eq!(meta.dependencies.len(), 2);
+        let api = meta.dependencies.iter().find(|d| d.id == "fabric-api").unwrap();
+        assert!(api.required);
+        let modmenu = meta.dependencies.iter().find(|d| d.id == "modmenu").unwrap();
+        assert!(!modmenu.required);
+        assert_eq!(modmenu.version.as_deref(), Some(">=9"));
+    }
+
+    #[test]
+    fn fabric_mod_json_without_id() {
+        assert!(parse_fabric(r#"{ "version": "1.0" }"#).is_err());
+    }
+
+    #[test]
+    fn mods_toml() {
+        let meta = parse_mods_toml(
+            r#"
+modLoader = "javafml"
+loaderVersion = "[47,)"
+
+[[mods]]
+modId = "jei"
+version = "${file.jarVersion}"
+displayName = "Just Enough Items"
+
+[[dependencies.jei]]
+modId = "forge"
+mandatory = true
+versionRange = "[47.1.3,)"
+
+[[dependencies.jei]]
+modId = "minecraft"
+mandatory = true
+versionRange = "[1.20.1,1.20.2)"
+
+[[dependencies.jei]]
+modId = "appleskin"
+type = "optional"
+"#,
+            JarKind::Forge,
+            Some("Manifest-Version: 1.0\r\nImplementation-Version: 15.2.0.27\r\n"),
+        )
+        .unwrap();
+
+        assert_eq!(meta.id, "jei");
+        assert_eq!(meta.name.as_deref(), Some("Just Enough Items"));
+        assert_eq!(meta.version.as_deref(), Some("15.2.0.27"));
+        assert_eq!(meta.minecraft.as_deref(), Some("[1.20.1,1.20.2)"));
+        assert_eq!(meta.loader.as_deref(), Some("[47.1.3,)"));
+        assert_eq!(meta.dependencies.len(), 1);
+        assert!(!meta.dependencies[0].required);
+    }
+
+    #[test]
+    fn mods_toml_falls_back_to_loader_version() {
+        let meta = parse_mods_toml(
+            "loaderVersion = \"[2,)\"\n[[mods]]\nmodId = \"ferritecore\"\nversion = \"6.0.0\"\n",
+            JarKind::NeoForge,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(meta.version.as_deref(), Some("6.0.0"));
+        assert_eq!(meta.loader.as_deref(), Some("[2,)"));
+        assert!(parse_mods_toml("modLoader = \"javafml\"\n", JarKind::Forge, None).is_err());
+    }
+
+    #[test]
+    fn plugin_yml() {
+        let meta = parse_plugin_yml(
+            "name: Essentials\nversion: 1.20\napi-version: 1.20 # comment\ndepend: [Vault]\nsoftdepend:\n  - LuckPerms\n",
+            JarKind::Bukkit,
+        )
+        .unwrap();
+
+        assert_eq!(meta.id, "Essentials");
+        // Kept verbatim rather than read as the number 1.2
+        assert_eq!(meta.version.as_deref(), Some("1.20"));
+        assert_eq!(meta.minecraft.as_deref(), Some(">=1.20"));
+        assert_eq!(meta.dependencies.len(), 2);
+        assert!(meta.dependencies.iter().any(|d| d.id == "Vault" && d.required));
+        assert!(meta.dependencies.iter().any(|d| d.id == "LuckPerms" && !d.required));
+    }
+
+    #[test]
+    fn paper_plugin_yml() {
+        let meta = parse_plugin_yml(
+            "name: Chunky\nversion: '1.4.0'\ndependencies:\n  server:\n    WorldBorder:\n      required: false\n    Vault: {}\n",
+            JarKind::PaperPlugin,
+        )
+        .unwrap();
+
+        assert_eq!(meta.version.as_deref(), Some("1.4.0"));
+        assert!(meta.minecraft.is_none());
+        assert!(meta.dependencies.iter().any(|d| d.id == "WorldBorder" && !d.required));
+        assert!(meta.dependencies.iter().any(|d| d.id == "Vault" && d.required));
+    }
+
+    #[test]
+    fn inspect_prefers_mod_metadata() {
+        let dir = crate::testutil::temp_dir();
+        let path = dir.path().join("both.jar");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, contents) in [
+            ("plugin.yml", "name: Both\nversion: 1\n"),
+            ("fabric.mod.json", r#"{ "id": "both", "version": "2" }"#),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let meta = inspect(&path).unwrap().unwrap();
+        assert_eq!(meta.kind, JarKind::Fabric);
+        assert_eq!(meta.id, "both");
+    }
+}
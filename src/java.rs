@@ -0,0 +1,396 @@
+use anyhow::{Context, Result, anyhow};
+use flate2::read::GzDecoder;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::cache;
+use crate::checksum::{Algorithm, Checksum};
+use crate::config::{ServerConfig, ServerType};
+use crate::global;
+use crate::http;
+use crate::metadata;
+use crate::vanilla;
+use crate::version;
+
+const ADOPTIUM_API: &str = "https://api.adoptium.net/v3";
+
+#[cfg(windows)]
+const JAVA_BIN: &str = "java.exe";
+#[cfg(not(windows))]
+const JAVA_BIN: &str = "java";
+
+pub struct JavaInstall {
+    pub path: PathBuf,
+    pub major: u32,
+    pub version: String,
+}
+
+// Directories that each hold one JDK per subdirectory
+fn jdk_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = managed_dir().into_iter().collect();
+    roots.extend([
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/Library/Java/JavaVirtualMachines"),
+    ]);
+
+    match std::env::var_os("SDKMAN_DIR") {
+        Some(dir) => roots.push(PathBuf::from(dir).join("candidates").join("java")),
+        None => {
+            if let Some(home) = std::env::home_dir() {
+                roots.push(home.join(".sdkman").join("candidates").join("java"));
+            }
+        }
+    }
+
+    roots
+}
+
+fn candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Some(home) = std::env::var_os("JAVA_HOME").filter(|h| !h.is_empty()) {
+        candidates.push(PathBuf::from(home).join("bin").join(JAVA_BIN));
+    }
+
+    if let Some(path) = std::env::var_os("PATH") {
+        candidates.extend(std::env::split_paths(&path).map(|dir| dir.join(JAVA_BIN)));
+    }
+
+    for root in jdk_roots() {
+        let Ok(entries) = fs::read_dir(&root) else {
+            continue;
+        };
+        let mut homes: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        homes.sort();
+        for home in homes {
+            candidates.push(home.join("bin").join(JAVA_BIN));
+            // macOS bundles keep the JDK under Contents/Home
+            candidates.push(home.join("Contents").join("Home").join("bin").join(JAVA_BIN));
+        }
+    }
+
+    candidates
+}
+
+// Parses the quoted version out of `java -version` output, e.g.
+// `openjdk version "17.0.8" 2023-07-18` or `java version "1.8.0_382"`
+fn parse_version(output: &str) -> Option<(u32, String)> {
+    let line = output.lines().find(|l| l.contains(" version \""))?;
+    let version = line.split('"').nth(1)?.to_string();
+
+    let mut parts = version.split(['.', '_', '-', '+']);
+    let first: u32 = parts.next()?.parse().ok()?;
+    let major = if first == 1 { parts.next()?.parse().ok()? } else { first };
+
+    Some((major, version))
+}
+
+pub fn inspect(java: &Path) -> Option<JavaInstall> {
+    let output = Command::new(java).arg("-version").output().ok()?;
+    // `java -version` prints to stderr
+    let text = String::from_utf8_lossy(&output.stderr);
+    let (major, version) = parse_version(&text)?;
+
+    Some(JavaInstall {
+        path: java.to_path_buf(),
+        major,
+        version,
+    })
+}
+
+// Every distinct Java runtime that can be found, newest first
+pub fn detect() -> Vec<JavaInstall> {
+    let mut seen = HashSet::new();
+    let mut installs = Vec::new();
+
+    for candidate in candidates() {
+        if !candidate.is_file() {
+            continue;
+        }
+        // PATH entries are usually symlinks into one of the JDK directories
+        let path = fs::canonicalize(&candidate).unwrap_or(candidate);
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        if let Some(install) = inspect(&path) {
+            installs.push(install);
+        }
+    }
+
+    installs.sort_by_key(|i| std::cmp::Reverse(i.major));
+    installs
+}
+
+// The Java major version Mojang lists for a Minecraft release. Releases from before
+// the field existed all ran on Java 8.
+pub fn required_major(minecraft_version: &str) -> Result<u32> {
+    let info = vanilla::get_version_info(minecraft_version)?;
+    Ok(info["javaVersion"]["majorVersion"]
+        .as_u64()
+        .map(|major| major as u32)
+        .unwrap_or(8))
+}
+
+// Old Forge releases crash on newer JVMs: anything before 1.13 only runs on Java 8,
+// and 1.13 to 1.16 on Java 8 to 11
+fn max_major(config: &ServerConfig) -> Option<u32> {
+    if config.server_type != ServerType::Forge {
+        return None;
+    }
+    if version::cmp_versions(&config.version, "1.13") == Ordering::Less {
+        Some(8)
+    } else if version::cmp_versions(&config.version, "1.17") == Ordering::Less {
+        Some(11)
+    } else {
+        None
+    }
+}
+
+// Prefers the exact major version Minecraft asks for, then the closest newer one
+// within `max`
+fn pick(installs: Vec<JavaInstall>, required: u32, max: Option<u32>) -> Option<JavaInstall> {
+    installs
+        .into_iter()
+        .filter(|i| i.major >= required && max.is_none_or(|max| i.major <= max))
+        .min_by_key(|i| i.major - required)
+}
+
+// Makes sure the server has a suitable java_path, detecting one when it's missing or
+// too old, and saves the choice to mcs.toml
+pub fn ensure(path: &Path, config: &ServerConfig) -> Result<ServerConfig> {
+    let required = match required_major(&config.version) {
+        Ok(required) => Some(required),
+        Err(e) => {
+            println!(
+                "⚠ Couldn't determine which Java Minecraft {} needs: {}",
+                config.version, e
+            );
+            None
+        }
+    };
+
+    let max = max_major(config);
+
+    if let Some(java) = &config.java_path {
+        match inspect(java) {
+            Some(install) if install.major > max.unwrap_or(u32::MAX) => println!(
+                "⚠ {} is Java {}, but {} {} only runs on Java {} or older",
+                java.display(),
+                install.major,
+                config.server_type,
+                config.version,
+                max.unwrap_or_default()
+            ),
+            Some(install) if required.is_none_or(|r| install.major >= r) => {
+                return Ok(config.clone());
+            }
+            Some(install) => println!(
+                "⚠ {} is Java {}, but Minecraft {} needs Java {}",
+                java.display(),
+                install.major,
+                config.version,
+                required.unwrap_or_default()
+            ),
+            None => println!("⚠ {} isn't a working Java runtime", java.display()),
+        }
+    }
+
+    let installs = detect();
+    let found = match required {
+        Some(required) => match pick(installs, required, max) {
+            Some(install) => {
+                if install.major != required {
+                    println!(
+                        "⚠ Minecraft {} asks for Java {}, which isn't installed; using Java {} instead",
+                        config.version, required, install.major
+                    );
+                    println!("→ Run 'mcs java install {}' if the server fails to start", required);
+                }
+                install
+            }
+            None => {
+                println!("■ No suitable Java {} found, installing one...", required);
+                install(required).context(format!(
+                    "No suitable Java {} found. Install a JDK (or point JAVA_HOME at one), or set java_path in mcs.toml.",
+                    required
+                ))?
+            }
+        },
+        None => match installs.into_iter().find(|i| max.is_none_or(|max| i.major <= max)) {
+            Some(install) => install,
+            None => return Ok(config.clone()),
+        },
+    };
+
+    println!("✓ Using Java {} ({})", found.version, found.path.display());
+
+    let mut config = config.clone();
+    config.java_path = Some(found.path);
+    config.save(path)?;
+    Ok(config)
+}
+
+// Runtimes installed by `mcs java install` live in ~/.mcs/java/<major>/
+fn managed_dir() -> Option<PathBuf> {
+    global::mcs_home().ok().map(|home| home.join("java"))
+}
+
+fn java_in(home: &Path) -> Option<PathBuf> {
+    [
+        home.join("bin").join(JAVA_BIN),
+        home.join("Contents").join("Home").join("bin").join(JAVA_BIN),
+    ]
+    .into_iter()
+    .find(|p| p.is_file())
+}
+
+// Names as the Adoptium API expects them
+fn adoptium_platform() -> Result<(&'static str, &'static str)> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "mac",
+        "windows" => "windows",
+        other => return Err(anyhow!("Adoptium has no builds for {}", other)),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "aarch64",
+        "arm" => "arm",
+        "x86" => "x32",
+        "powerpc64" => "ppc64le",
+        "s390x" => "s390x",
+        other => return Err(anyhow!("Adoptium has no builds for {}", other)),
+    };
+    Ok((os, arch))
+}
+
+// Downloads the latest Temurin JDK for this platform into ~/.mcs/java/<major>/
+pub fn install(major: u32) -> Result<JavaInstall> {
+    let java_dir = managed_dir().ok_or_else(|| anyhow!("Could not determine the mcs home directory"))?;
+    let target = java_dir.join(major.to_string());
+
+    if let Some(install) = java_in(&target).and_then(|java| inspect(&java)) {
+        println!("✓ Java {} is already installed ({})", install.version, install.path.display());
+        return Ok(install);
+    }
+
+    let (os, arch) = adoptium_platform()?;
+    let client = http::client()?;
+
+    println!("⟳ Looking up the latest Temurin {} for {} {}...", major, os, arch);
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/assets/latest/{}/hotspot", ADOPTIUM_API, major),
+        &[
+            ("architecture", arch),
+            ("image_type", "jdk"),
+            ("os", os),
+            ("vendor", "eclipse"),
+        ],
+    )?;
+    let assets = metadata::get_json(&client, url.as_str())?
+        .ok_or_else(|| anyhow!("Java {} isn't available from Adoptium", major))?;
+
+    let asset = assets
+        .as_array()
+        .and_then(|a| a.first())
+        .ok_or_else(|| anyhow!("No Temurin {} build found for {} {}", major, os, arch))?;
+    let package = &asset["binary"]["package"];
+    let link = package["link"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Failed to get download url"))?;
+    let name = package["name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Failed to get package name"))?;
+    let sha256 = package["checksum"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Failed to get download checksum"))?;
+    let expected = Checksum::new(Algorithm::Sha256, sha256);
+
+    fs::create_dir_all(&java_dir)?;
+    let archive = java_dir.join(name);
+    println!("↓ Downloading {}...", name);
+    cache::fetch(&client, link, &archive, Some(&expected))?;
+
+    // Extract next to the target and only move it into place once complete
+    let staging = java_dir.join(format!(".{}.part", major));
+    let _ = fs::remove_dir_all(&staging);
+    let result = extract(&archive, &staging).and_then(|()| {
+        let home = single_subdir(&staging).unwrap_or_else(|| staging.clone());
+        let _ = fs::remove_dir_all(&target);
+        fs::rename(&home, &target).context(format!("Failed to move Java into {:?}", target))
+    });
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_file(&archive);
+    result?;
+
+    let install = java_in(&target)
+        .and_then(|java| inspect(&java))
+        .ok_or_else(|| anyhow!("The downloaded Java {} doesn't run on this system", major))?;
+    println!("✓ Installed Java {} (sha256 verified)", install.version);
+    Ok(install)
+}
+
+fn extract(archive: &Path, dest: &Path) -> Result<()> {
+    let file = File::open(archive).context(format!("Failed to read {:?}", archive))?;
+    let name = archive.to_string_lossy();
+
+    if name.ends_with(".zip") {
+        zip::ZipArchive::new(file)?
+            .extract(dest)
+            .context(format!("Failed to extract {:?}", archive))?;
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(dest)
+            .context(format!("Failed to extract {:?}", archive))?;
+    } else {
+        return Err(anyhow!("Unsupported archive format: {:?}", archive));
+    }
+
+    Ok(())
+}
+
+// Archives contain one top-level directory such as jdk-21.0.4+7
+fn single_subdir(dir: &Path) -> Option<PathBuf> {
+    let entries: Vec<_> = fs::read_dir(dir).ok()?.filter_map(|e| e.ok()).collect();
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => Some(entry.path()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::config;
+
+    fn installs(majors: &[u32]) -> Vec<JavaInstall> {
+        majors
+            .iter()
+            .map(|&major| JavaInstall {
+                path: PathBuf::from(format!("/jdk{}/bin/java", major)),
+                major,
+                version: major.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picks_exact_then_closest_newer() {
+        assert_eq!(pick(installs(&[21, 17, 8]), 17, None).map(|i| i.major), Some(17));
+        assert_eq!(pick(installs(&[25, 21]), 17, None).map(|i| i.major), Some(21));
+        assert_eq!(pick(installs(&[11]), 17, None).map(|i| i.major), None);
+    }
+
+    #[test]
+    fn legacy_forge_has_an_upper_bound() {
+        assert_eq!(max_major(&config(ServerType::Forge, "1.12.2")), Some(8));
+        assert_eq!(max_major(&config(ServerType::Forge, "1.16.5")), Some(11));
+        assert_eq!(max_major(&config(ServerType::Forge, "1.20.1")), None);
+        assert_eq!(max_major(&config(ServerType::Paper, "1.12.2")), None);
+
+        assert_eq!(pick(installs(&[21, 17]), 8, Some(8)).map(|i| i.major), None);
+        assert_eq!(pick(installs(&[21, 11]), 8, Some(11)).map(|i| i.major), Some(11));
+    }
+}
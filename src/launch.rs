@@ -0,0 +1,268 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::{FlagPreset, ServerConfig};
+use crate::java;
+use crate::paper;
+use crate::system;
+use crate::units::{self, MemorySize};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+// https://docs.papermc.io/paper/aikars-flags
+const AIKAR_FLAGS: &[&str] = &[
+    "-XX:+UseG1GC",
+    "-XX:+ParallelRefProcEnabled",
+    "-XX:MaxGCPauseMillis=200",
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+DisableExplicitGC",
+    "-XX:+AlwaysPreTouch",
+    "-XX:G1NewSizePercent=30",
+    "-XX:G1MaxNewSizePercent=40",
+    "-XX:G1HeapRegionSize=8M",
+    "-XX:G1ReservePercent=20",
+    "-XX:G1HeapWastePercent=5",
+    "-XX:G1MixedGCCountTarget=4",
+    "-XX:InitiatingHeapOccupancyPercent=15",
+    "-XX:G1MixedGCLiveThresholdPercent=90",
+    "-XX:G1RSetUpdatingPauseTimePercent=5",
+    "-XX:SurvivorRatio=32",
+    "-XX:+PerfDisableSharedMem",
+    "-XX:MaxTenuringThreshold=1",
+    "-Dusing.aikars.flags=https://mcflags.emc.gs",
+    "-Daikars.new.flags=true",
+];
+
+// Aikar's values for heaps above 12G replace these G1 settings
+const AIKAR_LARGE_HEAP: &[(&str, &str)] = &[
+    ("-XX:G1NewSizePercent=", "40"),
+    ("-XX:G1MaxNewSizePercent=", "50"),
+    ("-XX:G1HeapRegionSize=", "16M"),
+    ("-XX:G1ReservePercent=", "15"),
+    ("-XX:InitiatingHeapOccupancyPercent=", "20"),
+];
+
+const ZGC_FLAGS: &[&str] = &[
+    "-XX:+UseZGC",
+    "-XX:+AlwaysPreTouch",
+    "-XX:+DisableExplicitGC",
+    "-XX:+PerfDisableSharedMem",
+];
+
+// Everything needed to start the server: the scripts render it, and it's the single
+// place that turns mcs.toml into a command line
+pub struct Launch {
+    pub java: String,
+    pub jvm_args: Vec<String>,
+    pub target: Target,
+    pub server_args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+pub enum Target {
+    Jar(String),
+    // Forge 1.17+ and NeoForge ship no runnable jar; their installer writes the
+    // classpath and main class into argument files under libraries/ instead
+    ArgFiles { unix: String, windows: String },
+}
+
+pub const USER_JVM_ARGS: &str = "user_jvm_args.txt";
+
+impl Launch {
+    // With argument files, the JVM arguments live in user_jvm_args.txt
+    pub fn args(&self, windows: bool) -> Vec<String> {
+        let (jvm_args, target) = match &self.target {
+            Target::Jar(jar) => (self.jvm_args.clone(), vec!["-jar".to_string(), jar.clone()]),
+            Target::ArgFiles { unix, windows: win } => (
+                vec![format!("@{}", USER_JVM_ARGS)],
+                vec![format!("@{}", if windows { win } else { unix })],
+            ),
+        };
+        jvm_args
+            .into_iter()
+            .chain(target)
+            .chain(self.server_args.iter().cloned())
+            .collect()
+    }
+}
+
+fn version_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+// Finds the argument files of the Forge or NeoForge build installed for this Minecraft
+// version. Forge names its directory "1.20.1-47.2.0", NeoForge drops the leading "1."
+// ("20.4.237" for 1.20.4).
+fn find_arg_files(path: &Path, minecraft_version: &str) -> Option<Target> {
+    let forge_prefix = format!("{}-", minecraft_version);
+    // 1.21 is "21.0.x", 1.21.1 is "21.1.x"
+    let neoforge_prefix = match minecraft_version.trim_start_matches("1.") {
+        minor if minor.contains('.') => format!("{}.", minor),
+        minor => format!("{}.0.", minor),
+    };
+
+    let libraries = path.join("libraries").join("net");
+    let mut candidates: Vec<PathBuf> = version_dirs(&libraries.join("minecraftforge").join("forge"))
+        .into_iter()
+        .filter(|dir| dir.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&forge_prefix)))
+        .collect();
+    candidates.extend(
+        version_dirs(&libraries.join("neoforged").join("neoforge"))
+            .into_iter()
+            .filter(|dir| dir.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&neoforge_prefix))),
+    );
+
+    // Several builds can be installed side by side; the newest install wins
+    let dir = candidates
+        .into_iter()
+        .filter(|dir| dir.join("unix_args.txt").is_file())
+        .max_by_key(|dir| {
+            fs::metadata(dir.join("unix_args.txt"))
+                .and_then(|m| m.modified())
+                .ok()
+        })?;
+
+    let relative = dir.strip_prefix(path).ok()?.to_string_lossy().replace('\\', "/");
+    Some(Target::ArgFiles {
+        unix: format!("{}/unix_args.txt", relative),
+        windows: format!("{}/win_args.txt", relative).replace('/', "\\"),
+    })
+}
+
+fn aikar_flags(max_heap: Option<u64>) -> Vec<String> {
+    let large = max_heap.is_some_and(|bytes| bytes > 12 * GIB);
+    AIKAR_FLAGS
+        .iter()
+        .map(|flag| {
+            let large_value = AIKAR_LARGE_HEAP
+                .iter()
+                .find(|(prefix, _)| large && flag.starts_with(prefix));
+            match large_value {
+                Some((prefix, value)) => format!("{}{}", prefix, value),
+                None => flag.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn preset_flags(config: &ServerConfig, java_major: Option<u32>, max_heap: Option<u64>) -> Vec<String> {
+    match config.flag_preset() {
+        FlagPreset::Aikar => aikar_flags(max_heap),
+        FlagPreset::Zgc => {
+            let mut flags: Vec<String> = ZGC_FLAGS.iter().map(|f| f.to_string()).collect();
+            // Generational ZGC is opt-in on Java 21-22 and the only mode from 23 on;
+            // older runtimes refuse to start with the flag
+            if matches!(java_major, Some(21 | 22)) {
+                flags.push("-XX:+ZGenerational".to_string());
+            }
+            flags
+        }
+        FlagPreset::PaperApi => match paper::get_recommended_flags(&config.version) {
+            Ok(flags) if !flags.is_empty() => {
+                println!("✓ Using Paper's recommended JVM flags for {}", config.version);
+                flags
+            }
+            _ => {
+                println!("⚠ Couldn't fetch Paper's recommended JVM flags, using Aikar's flags");
+                aikar_flags(max_heap)
+            }
+        },
+        FlagPreset::None => Vec::new(),
+    }
+}
+
+// Percentages become the JVM's RAM percentage flags, so they follow the machine
+// (or container) the server actually runs on
+fn memory_args(min: MemorySize, max: MemorySize) -> Vec<String> {
+    let min = match min {
+        MemorySize::Percent(percent) => format!("-XX:InitialRAMPercentage={}", percent),
+        size => format!("-Xms{}", size),
+    };
+    let max = match max {
+        MemorySize::Percent(percent) => format!("-XX:MaxRAMPercentage={}", percent),
+        size => format!("-Xmx{}", size),
+    };
+    vec![min, max]
+}
+
+// Fails on settings the JVM would refuse, warns about ones it would run badly with
+fn check_memory(config: &ServerConfig, total: Option<u64>, java_major: Option<u32>) -> Result<()> {
+    let min = config.min_memory().bytes(total);
+    let max = config.max_memory().bytes(total);
+
+    if let (Some(min), Some(max)) = (min, max)
+        && min > max
+    {
+        return Err(anyhow!(
+            "min_memory ({}) is larger than max_memory ({}), so the JVM would refuse to start",
+            config.min_memory(),
+            config.max_memory()
+        ));
+    }
+
+    let Some(max) = max else {
+        println!("⚠ Couldn't detect this machine's memory to check {}", config.max_memory());
+        return Ok(());
+    };
+
+    if let Some(total) = total
+        && max > total
+    {
+        println!(
+            "⚠ The server may use up to {}, but this machine only has {}",
+            units::format_size(max),
+            units::format_size(total)
+        );
+    }
+
+    // Above ~32G the JVM can no longer compress object pointers, so a 32-40G heap holds
+    // less than a 31G one
+    if (32 * GIB..48 * GIB).contains(&max) {
+        println!(
+            "⚠ Heaps from 32G to about 48G lose compressed object pointers; 31G is usually faster than {}",
+            config.max_memory()
+        );
+    }
+
+    match config.flag_preset() {
+        FlagPreset::Zgc if max < 8 * GIB => println!(
+            "⚠ ZGC needs headroom to collect concurrently; with less than 8G Aikar's flags usually perform better"
+        ),
+        FlagPreset::Zgc if java_major.is_some_and(|major| major < 17) => println!(
+            "⚠ ZGC is experimental before Java 17; consider Aikar's flags on this runtime"
+        ),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn build(path: &Path, config: &ServerConfig) -> Result<Launch> {
+    let total = system::total_memory();
+    let java_major = config
+        .java_path
+        .as_deref()
+        .and_then(java::inspect)
+        .map(|install| install.major);
+
+    check_memory(config, total, java_major)?;
+
+    let mut jvm_args = memory_args(config.min_memory(), config.max_memory());
+    jvm_args.extend(preset_flags(config, java_major, config.max_memory().bytes(total)));
+    jvm_args.extend(config.jvm_args.iter().cloned());
+
+    let mut server_args = vec!["nogui".to_string()];
+    server_args.extend(config.server_args.iter().cloned());
+
+    Ok(Launch {
+        java: config.java(),
+        jvm_args,
+        target: find_arg_files(path, &config.version)
+            .unwrap_or_else(|| Target::Jar("server.jar".to_string())),
+        server_args,
+        env: config.env.clone(),
+    })
+}
@@ -8,15 +8,54 @@ mod forge;
 mod prompt;
 mod setup;
 mod modrinth;
+mod version;
+mod jar;
+mod compat;
+mod upgrade;
+mod checksum;
+mod download;
+mod global;
+mod units;
+mod cache;
+mod metadata;
+mod http;
+mod parallel;
+mod java;
+mod launch;
+mod system;
+mod restart;
+mod supervisor;
+mod control;
+mod properties;
+mod overlay;
+mod rcon;
+mod chat;
+mod ping;
+mod query;
+mod registry;
+mod ports;
+#[cfg(test)]
+mod testutil;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result, anyhow};
+use clap::{ArgGroup, Parser, Subcommand};
+use config::{FlagPreset, ServerConfig, ServerType};
 use std::path::PathBuf;
+use units::MemorySize;
 
 #[derive(Parser)]
 #[command(name = "mcs")]
 #[command(about = "A CLI for creating Minecraft servers", long_about = None)]
 struct Cli {
+    /// Use cached metadata only and never contact the APIs
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Maximum number of downloads to run at once
+    #[arg(long, short = 'j', global = true, default_value_t = parallel::DEFAULT_JOBS)]
+    jobs: usize,
+    /// Run the command for a server from 'mcs servers' instead of the current directory
+    #[arg(long, global = true, value_name = "NAME")]
+    server: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -25,20 +64,162 @@ struct Cli {
 enum Commands {
     New {
         path: PathBuf,
+        #[arg(long = "type", requires = "version")]
+        server_type: Option<ServerType>,
+        #[arg(long, requires = "server_type")]
+        version: Option<String>,
+        #[arg(long, default_value = "2G")]
+        memory: MemorySize,
+        #[arg(long, requires = "server_type")]
+        flags: Option<FlagPreset>,
     },
     Apply,
     Configure,
     Add {
-        name: String,
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    List,
+    // Either report what would change (--plan) or do it (--execute)
+    #[command(group(ArgGroup::new("mode").required(true).args(["plan", "execute"])))]
+    Upgrade {
+        #[arg(long)]
+        to: String,
+        #[arg(long, conflicts_with = "execute")]
+        plan: bool,
+        #[arg(long)]
+        execute: bool,
+        #[arg(long, requires = "execute")]
+        force: bool,
+    },
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    Java {
+        #[command(subcommand)]
+        command: JavaCommand,
+    },
+    Tune {
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Run the server in the foreground, restarting it according to mcs.toml
+    Run {
+        /// How long to wait for the server to save and stop before killing it
+        #[arg(long, default_value = "60s")]
+        stop_timeout: String,
+    },
+    /// Start the server, in the background with --detach
+    Start {
+        #[arg(long, short = 'd')]
+        detach: bool,
+        /// How long to wait for the server to save and stop before killing it
+        #[arg(long, default_value = "60s")]
+        stop_timeout: String,
+    },
+    /// Open the console of a server running in the background
+    Attach,
+    /// Stop a running server
+    Stop {
+        /// How long to wait for the server to save and stop before killing it
+        #[arg(long, default_value = "60s")]
+        timeout: String,
+    },
+    /// Show whether the server is running, with its PID, uptime and memory
+    Status,
+    /// Run a console command on the running server over RCON
+    Exec {
+        #[arg(required_unless_present = "interactive", conflicts_with = "interactive")]
+        command: Vec<String>,
+        /// Read commands one per line until exit
+        #[arg(long, short = 'i')]
+        interactive: bool,
+    },
+    /// Query a server's version, players and MOTD over the Server List Ping protocol
+    Ping {
+        /// host or host:port; defaults to this server's server-port
+        address: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Query a server's full player and plugin list over the UDP Query protocol
+    Query {
+        /// host or host:port; defaults to this server's query.port
+        address: Option<String>,
+    },
+    /// List every server mcs has set up, with its type, version and state
+    Servers,
+    /// Read and change server.properties
+    Props {
+        #[command(subcommand)]
+        command: PropsCommand,
+    },
+    #[command(hide = true)]
+    Supervise {
+        #[arg(long)]
+        stop_timeout: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    Ls,
+    Clean,
+    Prune {
+        #[arg(long)]
+        older_than: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PropsCommand {
+    List,
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum JavaCommand {
+    List,
+    Install {
+        major: u32,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    metadata::set_offline(cli.offline);
+    parallel::set_jobs(cli.jobs);
+
+    // Every command works on the current directory, so targeting a server means
+    // switching to it
+    if let Some(name) = &cli.server {
+        if matches!(cli.command, Commands::New { .. }) {
+            return Err(anyhow!("--server can't be used with 'mcs new'; pass the path of the new server instead"));
+        }
+        let entry = registry::find(name)?;
+        std::env::set_current_dir(&entry.path)
+            .context(format!("Failed to switch to {}", entry.path.display()))?;
+    }
 
     match cli.command {
-        Commands::New { path } => {
-            commands::create_new_server(&path)?;
+        Commands::New { path, server_type, version, memory, flags } => {
+            let preset = server_type
+                .zip(version)
+                .map(|(server_type, version)| {
+                    let flags = flags.unwrap_or(match server_type {
+                        ServerType::Paper => FlagPreset::PaperApi,
+                        _ => FlagPreset::None,
+                    });
+                    ServerConfig::new(version, server_type, memory, flags)
+                });
+            commands::create_new_server(&path, preset)?;
         }
         Commands::Apply => {
             commands::apply_config()?;
@@ -46,8 +227,63 @@ fn main() -> Result<()> {
         Commands::Configure => {
             commands::reconfigure_server()?;
         }
-        Commands::Add { name } => {
-            commands::add_content(&name)?;
+        Commands::Add { names } => {
+            commands::add_content(&names)?;
+        }
+        Commands::List => {
+            commands::list_content()?;
+        }
+        Commands::Upgrade { to, plan, execute: _, force } => {
+            commands::upgrade_server(&to, plan, force)?;
+        }
+        Commands::Cache { command } => match command {
+            CacheCommand::Ls => commands::list_cache()?,
+            CacheCommand::Clean => commands::clean_cache()?,
+            CacheCommand::Prune { older_than } => commands::prune_cache(&older_than)?,
+        },
+        Commands::Java { command } => match command {
+            JavaCommand::List => commands::list_java()?,
+            JavaCommand::Install { major } => {
+                java::install(major)?;
+            }
+        },
+        Commands::Tune { apply } => {
+            commands::tune(apply)?;
+        }
+        Commands::Run { stop_timeout } => {
+            commands::run_server(&stop_timeout)?;
+        }
+        Commands::Start { detach, stop_timeout } => {
+            commands::start_server(detach, &stop_timeout)?;
+        }
+        Commands::Attach => {
+            commands::attach_console()?;
+        }
+        Commands::Stop { timeout } => {
+            commands::stop_server(&timeout)?;
+        }
+        Commands::Status => {
+            commands::server_status()?;
+        }
+        Commands::Exec { command, interactive } => {
+            commands::exec_command(&command, interactive)?;
+        }
+        Commands::Ping { address, json } => {
+            commands::ping_server(address.as_deref(), json)?;
+        }
+        Commands::Query { address } => {
+            commands::query_server(address.as_deref())?;
+        }
+        Commands::Servers => {
+            commands::list_servers()?;
+        }
+        Commands::Props { command } => match command {
+            PropsCommand::List => commands::list_properties()?,
+            PropsCommand::Get { key } => commands::get_property(&key)?,
+            PropsCommand::Set { key, value } => commands::set_property(&key, &value)?,
+        },
+        Commands::Supervise { stop_timeout } => {
+            commands::supervise_detached(&stop_timeout)?;
         }
     }
 
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::global;
+use crate::http;
+use crate::units;
+
+// How long cached metadata is used before asking the API again
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    url: String,
+    fetched_at: u64,
+    body: Value,
+}
+
+fn cache_path(url: &str) -> Result<PathBuf> {
+    let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+    Ok(global::mcs_home()?
+        .join("cache")
+        .join("meta")
+        .join(format!("{}.json", key)))
+}
+
+fn read_cached(url: &str) -> Option<(Value, Duration)> {
+    let contents = fs::read_to_string(cache_path(url).ok()?).ok()?;
+    let cached: CachedResponse = serde_json::from_str(&contents).ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let age = Duration::from_secs(now.saturating_sub(cached.fetched_at));
+    Some((cached.body, age))
+}
+
+fn write_cached(url: &str, body: &Value) -> Result<()> {
+    let path = cache_path(url)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let cached = CachedResponse {
+        url: url.to_string(),
+        fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        body: body.clone(),
+    };
+    fs::write(&path, serde_json::to_string(&cached)?)?;
+    Ok(())
+}
+
+pub fn offline_error(what: &str) -> anyhow::Error {
+    anyhow!(
+        "Offline mode: {} is not cached. Run the command once without --offline to cache it.",
+        what
+    )
+}
+
+// GETs JSON metadata through the on-disk cache. Returns Ok(None) for a 404.
+// Fresh cache entries are used as-is; stale ones are the fallback when the API is
+// unreachable, and the only source in offline mode.
+pub fn get_json(client: &Client, url: &str) -> Result<Option<Value>> {
+    get(client, url, true)
+}
+
+// Same as get_json for plain-text responses such as Maven .sha1 files
+pub fn get_text(client: &Client, url: &str) -> Result<Option<String>> {
+    Ok(get(client, url, false)?.map(|body| match body {
+        Value::String(text) => text,
+        other => other.to_string(),
+    }))
+}
+
+fn get(client: &Client, url: &str, json: bool) -> Result<Option<Value>> {
+    let cached = read_cached(url);
+
+    if is_offline() {
+        return cached
+            .map(|(body, _)| Some(body))
+            .ok_or_else(|| offline_error(url));
+    }
+
+    if let Some((body, age)) = &cached
+        && *age < MAX_AGE
+    {
+        return Ok(Some(body.clone()));
+    }
+
+    let response = match http::send(client.get(url)) {
+        Ok(response) => response,
+        Err(e) => return stale_or(cached, url, anyhow!(e)),
+    };
+
+    if response.status().as_u16() == 404 {
+        return Ok(None);
+    }
+
+    if response.status().is_server_error() {
+        let status = response.status();
+        return stale_or(cached, url, anyhow!("HTTP {} from {}", status, url));
+    }
+
+    if !response.status().is_success() {
+        return Err(anyhow!("HTTP {} from {}", response.status(), url));
+    }
+
+    let body = if json {
+        response
+            .json()
+            .context(format!("Failed to parse response from {}", url))?
+    } else {
+        Value::String(response.text()?)
+    };
+
+    if let Err(e) = write_cached(url, &body) {
+        println!("⚠ Couldn't cache metadata from {}: {}", url, e);
+    }
+
+    Ok(Some(body))
+}
+
+fn stale_or(cached: Option<(Value, Duration)>, url: &str, error: anyhow::Error) -> Result<Option<Value>> {
+    match cached {
+        Some((body, age)) => {
+            println!(
+                "⚠ Couldn't reach {}, using cached metadata from {} ago",
+                url,
+                units::format_duration(age)
+            );
+            Ok(Some(body))
+        }
+        None => Err(error.context(format!("Failed to fetch {}", url))),
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::{anyhow, Context, Result};
-use reqwest::blocking::Client;
 use serde::Deserialize;
-use std::fs;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::checksum::{Algorithm, Checksum};
+use crate::cache;
+use crate::http;
+use crate::metadata;
+use crate::download;
+use crate::parallel;
 
 const BASE_URL: &str = "https://api.modrinth.com/v2";
-const USER_AGENT: &str = "mcs/0.2.0 (github.com/dxkyy/mcs)";
 
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
@@ -19,6 +23,7 @@ pub struct ModrinthProject {
 #[allow(dead_code)]
 pub struct ModrinthVersion {
     pub id: String,
+    pub project_id: String,
     pub name: String,
     pub version_number: String,
     pub loaders: Vec<String>,
@@ -27,11 +32,34 @@ pub struct ModrinthVersion {
     pub dependencies: Vec<ModrinthDependency>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ModrinthFile {
     pub url: String,
     pub filename: String,
     pub primary: bool,
+    #[serde(default)]
+    pub hashes: ModrinthHashes,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ModrinthHashes {
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+}
+
+impl ModrinthFile {
+    pub fn checksum(&self) -> Option<Checksum> {
+        self.hashes
+            .sha512
+            .as_deref()
+            .map(|h| Checksum::new(Algorithm::Sha512, h))
+            .or_else(|| {
+                self.hashes
+                    .sha1
+                    .as_deref()
+                    .map(|h| Checksum::new(Algorithm::Sha1, h))
+            })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,80 +68,160 @@ pub struct ModrinthDependency {
     pub dependency_type: String,
 }
 
-fn build_client() -> Result<Client> {
-    Client::builder()
-        .user_agent(USER_AGENT)
-        .build()
-        .context("Failed to build HTTP client")
+fn api_url(path: &str, query: &[(&str, &str)]) -> Result<String> {
+    let url = reqwest::Url::parse_with_params(&format!("{}/{}", BASE_URL, path), query)
+        .context("Failed to build Modrinth URL")?;
+    Ok(url.to_string())
 }
 
 pub fn get_project_versions(slug: &str, loader: &str, game_version: &str) -> Result<Vec<ModrinthVersion>> {
-    let client = build_client()?;
+    let client = http::client()?;
 
     let loaders = serde_json::json!([loader]).to_string();
     let game_versions = serde_json::json!([game_version]).to_string();
 
-    let response = client
-        .get(format!("{}/project/{}/version", BASE_URL, slug))
-        .query(&[
+    let url = api_url(
+        &format!("project/{}/version", slug),
+        &[
             ("loaders", loaders.as_str()),
             ("game_versions", game_versions.as_str()),
-        ])
-        .send()
-        .context("Failed to fetch versions from Modrinth")?;
+        ],
+    )?;
 
-    if !response.status().is_success() {
+    let Some(data) = metadata::get_json(&client, &url)
+        .context("Failed to fetch versions from Modrinth")?
+    else {
         return Ok(vec![]);
-    }
+    };
 
-    let versions: Vec<ModrinthVersion> = response
-        .json()
-        .context("Failed to parse versions response")?;
+    let versions: Vec<ModrinthVersion> =
+        serde_json::from_value(data).context("Failed to parse versions response")?;
 
     Ok(versions)
 }
 
-pub fn download_file(url: &str, dest_path: &Path) -> Result<()> {
-    let client = build_client()?;
+pub fn download_file(file: &ModrinthFile, dest_path: &Path) -> Result<()> {
+    let client = http::client()?;
 
-    let response = client
-        .get(url)
-        .send()
-        .context("Failed to download file")?;
+    let expected = file
+        .checksum()
+        .ok_or_else(|| anyhow!("Modrinth didn't provide a hash for {}", file.filename))?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Failed to download file: HTTP {}", response.status()));
-    }
+    cache::fetch(&client, &file.url, dest_path, Some(&expected))
+}
 
-    let bytes = response.bytes().context("Failed to read download response")?;
-    fs::write(dest_path, bytes)
-        .context(format!("Failed to write file to {:?}", dest_path))?;
+// Downloads several files at once, limited by --jobs. Every file is attempted even
+// when another fails; failed files never end up at their destination.
+pub fn download_files(files: Vec<(ModrinthFile, PathBuf)>) -> Result<()> {
+    let total = files.len();
+    let names: Vec<String> = files.iter().map(|(f, _)| f.filename.clone()).collect();
 
+    let results = parallel::map(files, |(file, dest)| download_file(&file, &dest))?;
+
+    let mut failed = 0;
+    for (name, result) in names.iter().zip(results) {
+        if let Err(e) = result {
+            download::status(format!("✗ {}: {:#}", name, e));
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} of {} downloads failed", failed, total));
+    }
     Ok(())
 }
 
-pub fn get_project(slug: &str) -> Result<Option<ModrinthProject>> {
-    let client = build_client()?;
+pub fn get_version_from_hash(sha1: &str) -> Result<Option<ModrinthVersion>> {
+    let client = http::client()?;
 
-    let response = client
-        .get(format!("{}/project/{}", BASE_URL, slug))
-        .send()
-        .context("Failed to fetch project from Modrinth")?;
+    let url = api_url(&format!("version_file/{}", sha1), &[("algorithm", "sha1")])?;
 
-    if response.status().as_u16() == 404 {
+    let Some(data) = metadata::get_json(&client, &url)
+        .context("Failed to look up file on Modrinth")?
+    else {
         return Ok(None);
+    };
+
+    let version: ModrinthVersion =
+        serde_json::from_value(data).context("Failed to parse version response")?;
+
+    Ok(Some(version))
+}
+
+pub fn get_versions_from_hashes(hashes: &[String]) -> Result<HashMap<String, ModrinthVersion>> {
+    if metadata::is_offline() {
+        return Err(metadata::offline_error("Modrinth's version lookup"));
+    }
+
+    let client = http::client()?;
+
+    let request = client
+        .post(format!("{}/version_files", BASE_URL))
+        .json(&serde_json::json!({
+            "hashes": hashes,
+            "algorithm": "sha1",
+        }));
+    let response = http::send(request).context("Failed to look up files on Modrinth")?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to look up files: HTTP {}",
+            response.status()
+        ));
     }
 
+    response
+        .json()
+        .context("Failed to parse version files response")
+}
+
+pub fn get_latest_versions_from_hashes(
+    hashes: &[String],
+    loader: &str,
+    game_version: &str,
+) -> Result<HashMap<String, ModrinthVersion>> {
+    if metadata::is_offline() {
+        return Err(metadata::offline_error("Modrinth's version lookup"));
+    }
+
+    let client = http::client()?;
+
+    let request = client
+        .post(format!("{}/version_files/update", BASE_URL))
+        .json(&serde_json::json!({
+            "hashes": hashes,
+            "algorithm": "sha1",
+            "loaders": [loader],
+            "game_versions": [game_version],
+        }));
+    let response = http::send(request).context("Failed to fetch updates from Modrinth")?;
+
     if !response.status().is_success() {
         return Err(anyhow!(
-            "Failed to fetch project: HTTP {}",
+            "Failed to fetch updates: HTTP {}",
             response.status()
         ));
     }
 
-    let project: ModrinthProject = response
+    response
         .json()
-        .context("Failed to parse project response")?;
+        .context("Failed to parse version files update response")
+}
+
+pub fn get_project(slug: &str) -> Result<Option<ModrinthProject>> {
+    let client = http::client()?;
+
+    let url = api_url(&format!("project/{}", slug), &[])?;
+
+    let Some(data) = metadata::get_json(&client, &url)
+        .context("Failed to fetch project from Modrinth")?
+    else {
+        return Ok(None);
+    };
+
+    let project: ModrinthProject =
+        serde_json::from_value(data).context("Failed to parse project response")?;
 
     Ok(Some(project))
 }
@@ -124,7 +232,7 @@ pub fn search_projects(
     loader: &str,
     game_version: &str,
 ) -> Result<Vec<ModrinthProject>> {
-    let client = build_client()?;
+    let client = http::client()?;
 
     let facets = serde_json::json!([
         [format!("project_type:{}", project_type)],
@@ -133,27 +241,19 @@ pub fn search_projects(
     ])
     .to_string();
 
-    let response = client
-        .get(format!("{}/search", BASE_URL))
-        .query(&[("query", query), ("facets", &facets), ("limit", "5")])
-        .send()
-        .context("Failed to search Modrinth")?;
+    let url = api_url("search", &[("query", query), ("facets", &facets), ("limit", "5")])?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "Modrinth search failed: HTTP {}",
-            response.status()
-        ));
-    }
+    let data = metadata::get_json(&client, &url)
+        .context("Failed to search Modrinth")?
+        .ok_or_else(|| anyhow!("Modrinth search failed: HTTP 404"))?;
 
     #[derive(Deserialize)]
     struct SearchResponse {
         hits: Vec<ModrinthProject>,
     }
 
-    let data: SearchResponse = response
-        .json()
-        .context("Failed to parse search response")?;
+    let data: SearchResponse =
+        serde_json::from_value(data).context("Failed to parse search response")?;
 
     Ok(data.hits)
 }
@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, anyhow};
+use serde_norway::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path};
+use crate::config::ServerConfig;
+
+// One setting of an overlay: the YAML path and the value to put there
+type Setting = (Vec<String>, Value);
+
+fn to_yaml(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s.clone()),
+        toml::Value::Integer(n) => Value::Number((*n).into()),
+        toml::Value::Float(f) => Value::Number((*f).into()),
+        toml::Value::Boolean(b) => Value::Bool(*b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Sequence(items.iter().map(to_yaml).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table
+                .iter()
+                .map(|(k, v)| (Value::String(k.clone()), to_yaml(v)))
+                .collect(),
+        ),
+    }
+}
+
+// Both `"a.b.c" = 1` and `a.b.c = 1` (which TOML reads as nested tables) become the
+// path a -> b -> c
+fn flatten(prefix: &[String], table: &toml::Table, settings: &mut Vec<Setting>) {
+    for (key, value) in table {
+        let mut path = prefix.to_vec();
+        path.extend(key.split('.').map(String::from));
+        match value {
+            toml::Value::Table(table) => flatten(&path, table, settings),
+            value => settings.push((path, to_yaml(value))),
+        }
+    }
+}
+
+// Overlays may only touch YAML files inside the server directory
+fn check_file(file: &str) -> Result<()> {
+    let path = Path::new(file);
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow!("files.\"{}\" must be a path inside the server directory", file));
+    }
+    if !file.ends_with(".yml") && !file.ends_with(".yaml") {
+        return Err(anyhow!("files.\"{}\" is not a YAML file; only .yml and .yaml files can be edited", file));
+    }
+    Ok(())
+}
+
+pub fn check_config(files: &BTreeMap<String, toml::Table>) -> Result<()> {
+    for file in files.keys() {
+        check_file(file).context("Invalid [files] in mcs.toml")?;
+    }
+    Ok(())
+}
+
+fn set(root: &mut Value, path: &[String], value: Value, file: &str) -> Result<()> {
+    let mut node = root;
+    for (i, key) in path.iter().enumerate() {
+        if node.is_null() {
+            *node = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(mapping) = node else {
+            return Err(anyhow!(
+                "{} in {} is not a section, so {} can't be set",
+                path[..i].join("."),
+                file,
+                path.join(".")
+            ));
+        };
+
+        let key = Value::String(key.clone());
+        if i == path.len() - 1 {
+            mapping.insert(key, value);
+            return Ok(());
+        }
+        node = mapping.entry(key).or_insert(Value::Null);
+    }
+    Ok(())
+}
+
+// One YAML file of the server, or Null when it doesn't exist. Only a missing file
+// counts as empty; anything unreadable is an error so it's left alone.
+pub fn read(server_dir: &Path, file: &str) -> Result<Value> {
+    check_file(file)?;
+    match fs::read_to_string(server_dir.join(file)) {
+        Ok(contents) => serde_norway::from_str(&contents).context(format!("Failed to parse {}", file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Value::Null),
+        Err(e) => Err(anyhow!(e).context(format!("Failed to read {}", file))),
+    }
+}
+
+// Deep-merges `table` into one YAML file of the server. Keys it doesn't mention are
+// kept; a file that doesn't exist yet is created. Returns the number of settings
+// written, or None when the file already had all of them and was left untouched.
+pub fn merge(server_dir: &Path, file: &str, table: &toml::Table) -> Result<Option<usize>> {
+    let original = read(server_dir, file)?;
+    let path = server_dir.join(file);
+
+    let mut settings = Vec::new();
+    flatten(&[], table, &mut settings);
+
+    let count = settings.len();
+    let mut merged = original.clone();
+    for (key, value) in settings {
+        set(&mut merged, &key, value, file)?;
+    }
+
+    // Rewriting drops comments, so files already in the desired state stay untouched
+    if merged == original {
+        return Ok(None);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let yaml = serde_norway::to_string(&merged)?;
+    fs::write(&path, yaml).context(format!("Failed to write {}", file))?;
+    Ok(Some(count))
+}
+
+// Applies every [files."<path>"] overlay from mcs.toml
+pub fn apply(server_dir: &Path, config: &ServerConfig) -> Result<()> {
+    for (file, table) in &config.files {
+        if let Some(count) = merge(server_dir, file, table)? {
+            println!("✓ Updated {} ({} setting{})", file, count, if count == 1 { "" } else { "s" });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerType;
+    use crate::testutil::temp_dir;
+
+    fn config(overlay: &str) -> ServerConfig {
+        let mut config = crate::testutil::config(ServerType::Paper, "1.21");
+        config.files = toml::from_str(overlay).unwrap();
+        config
+    }
+
+    #[test]
+    fn merges_into_existing_yaml() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::write(dir.join("bukkit.yml"), "settings:\n  allow-end: true\n  connection-throttle: 4000\n").unwrap();
+
+        apply(dir, &config("[\"bukkit.yml\"]\n\"settings.allow-end\" = false\nspawn-limits.monsters = 50\n")).unwrap();
+        let merged: Value = serde_norway::from_str(&fs::read_to_string(dir.join("bukkit.yml")).unwrap()).unwrap();
+
+        assert_eq!(merged["settings"]["allow-end"], Value::Bool(false));
+        assert_eq!(merged["settings"]["connection-throttle"], Value::Number(4000.into()));
+        assert_eq!(merged["spawn-limits"]["monsters"], Value::Number(50.into()));
+    }
+
+    #[test]
+    fn unreadable_file_is_left_alone() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let latin1 = b"settings:\n  motd: caf\xe9\n";
+        fs::write(dir.join("bukkit.yml"), latin1).unwrap();
+
+        let result = apply(dir, &config("[\"bukkit.yml\"]\n\"settings.allow-end\" = false\n"));
+        let contents = fs::read(dir.join("bukkit.yml")).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(contents, latin1);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_server() {
+        assert!(check_file("../bukkit.yml").is_err());
+        assert!(check_file("/etc/bukkit.yml").is_err());
+        assert!(check_file("server.properties").is_err());
+        assert!(check_file("config/paper-global.yml").is_ok());
+    }
+}
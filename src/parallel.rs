@@ -0,0 +1,111 @@
+use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+pub const DEFAULT_JOBS: usize = 4;
+
+static JOBS: AtomicUsize = AtomicUsize::new(DEFAULT_JOBS);
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+pub fn set_jobs(jobs: usize) {
+    JOBS.store(jobs.max(1), Ordering::Relaxed);
+}
+
+pub fn jobs() -> usize {
+    JOBS.load(Ordering::Relaxed)
+}
+
+// Built on first use, after --jobs has been read. The async side only waits on
+// the blocking tasks, so one thread drives it and the blocking pool gets one
+// thread per job.
+fn runtime() -> Result<&'static Runtime> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .max_blocking_threads(jobs())
+        .enable_all()
+        .build()?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+// Runs `f` over every item on a tokio runtime, at most `jobs()` at a time.
+// The work itself is blocking (reqwest::blocking, file IO), so each item gets its
+// own blocking task. Results come back in input order; one failure doesn't stop
+// the others.
+pub fn map<T, R, F>(items: Vec<T>, f: F) -> Result<Vec<Result<R>>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Result<R> + Send + Sync + 'static,
+{
+    if items.len() <= 1 {
+        return Ok(items.into_iter().map(f).collect());
+    }
+
+    let len = items.len();
+    runtime()?.block_on(async move {
+        let limit = Arc::new(Semaphore::new(jobs()));
+        let f = Arc::new(f);
+        let mut tasks = JoinSet::new();
+
+        for (index, item) in items.into_iter().enumerate() {
+            let limit = limit.clone();
+            let f = f.clone();
+            tasks.spawn(async move {
+                let _permit = limit.acquire_owned().await?;
+                let result = tokio::task::spawn_blocking(move || f(item)).await?;
+                Ok::<_, anyhow::Error>((index, result))
+            });
+        }
+
+        let mut results: Vec<Option<Result<R>>> = (0..len).map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined??;
+            results[index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.ok_or_else(|| anyhow!("Task did not complete")))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_order_and_limits_concurrency() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let (running_in, peak_in) = (running.clone(), peak.clone());
+        let results = map((0..12).collect(), move |n: u64| {
+            let now = running_in.fetch_add(1, Ordering::SeqCst) + 1;
+            peak_in.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20 * (n % 3)));
+            running_in.fetch_sub(1, Ordering::SeqCst);
+            if n == 5 { Err(anyhow!("failed")) } else { Ok(n * 2) }
+        })
+        .unwrap();
+
+        assert!(peak.load(Ordering::SeqCst) <= jobs());
+        assert_eq!(results.len(), 12);
+        for (n, result) in results.into_iter().enumerate() {
+            match n {
+                5 => assert!(result.is_err()),
+                _ => assert_eq!(result.unwrap(), n as u64 * 2),
+            }
+        }
+
+        // The runtime is reused by later calls
+        assert_eq!(map(vec![1, 2], Ok).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,418 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::chat;
+use crate::properties;
+
+pub const DEFAULT_PORT: u16 = 25565;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RESPONSE_LENGTH: usize = 1024 * 1024;
+// Any version: the server answers the status request regardless
+const STATUS_PROTOCOL: i32 = -1;
+// What 1.6 clients send in the legacy ping
+const LEGACY_PROTOCOL: u8 = 74;
+
+#[derive(Serialize)]
+pub struct ServerStatus {
+    pub version: String,
+    pub protocol: i64,
+    pub online: u64,
+    pub max: u64,
+    pub players: Vec<String>,
+    pub motd: String,
+    pub latency_ms: u64,
+    // Answered only the pre-1.7 ping
+    pub legacy: bool,
+}
+
+// "host", "host:port" or "[v6]:port"
+pub fn parse_address(address: &str, default_port: u16) -> Result<(String, u16)> {
+    let invalid = || anyhow!("Invalid address '{}'. Use host or host:port.", address);
+
+    if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => port.parse().map_err(|_| invalid())?,
+            None if rest.is_empty() => default_port,
+            None => return Err(invalid()),
+        };
+        return Ok((host.to_string(), port));
+    }
+
+    match address.split_once(':') {
+        Some((host, port)) if !port.contains(':') => {
+            Ok((host.to_string(), port.parse().map_err(|_| invalid())?))
+        }
+        // No port, or a bare IPv6 address
+        _ => Ok((address.to_string(), default_port)),
+    }
+}
+
+// Where a server managed by mcs listens, from its server.properties
+pub fn local_address(server_dir: &Path) -> Result<(String, u16)> {
+    let props = properties::load(server_dir)?;
+    let port = props
+        .get("server-port")
+        .map(|p| p.parse::<u16>().context(format!("Invalid server-port '{}' in server.properties", p)))
+        .transpose()?
+        .unwrap_or(DEFAULT_PORT);
+    let host = props
+        .get("server-ip")
+        .filter(|ip| !ip.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "localhost".to_string());
+    Ok((host, port))
+}
+
+fn connect(host: &str, port: u16) -> Result<TcpStream> {
+    let addresses: Vec<_> = (host, port)
+        .to_socket_addrs()
+        .context(format!("Failed to resolve {}", host))?
+        .collect();
+
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(anyhow!(e).context(format!("Failed to connect to {}:{}", host, port))),
+        None => Err(anyhow!("{} has no addresses", host)),
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let mut byte = [0];
+        reader.read_exact(&mut byte).context("Connection closed by the server")?;
+        value |= ((byte[0] & 0x7f) as u32) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(anyhow!("Invalid VarInt from the server"))
+}
+
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    write_varint(buf, s.len() as i32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn send_packet(stream: &mut TcpStream, id: i32, data: &[u8]) -> Result<()> {
+    let mut body = Vec::new();
+    write_varint(&mut body, id);
+    body.extend_from_slice(data);
+
+    let mut packet = Vec::new();
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    stream.write_all(&packet)?;
+    Ok(())
+}
+
+// Returns the packet id and its data
+fn read_packet(stream: &mut TcpStream) -> Result<(i32, Vec<u8>)> {
+    let length = read_varint(stream)?;
+    if length <= 0 || length as usize > MAX_RESPONSE_LENGTH {
+        return Err(anyhow!("Invalid packet length {}", length));
+    }
+    let mut body = vec![0; length as usize];
+    stream.read_exact(&mut body).context("Connection closed by the server")?;
+
+    let mut reader = body.as_slice();
+    let id = read_varint(&mut reader)?;
+    Ok((id, reader.to_vec()))
+}
+
+// https://minecraft.wiki/w/Java_Edition_protocol/Server_List_Ping
+fn ping_modern(host: &str, port: u16) -> Result<ServerStatus> {
+    let mut stream = connect(host, port)?;
+    let started = Instant::now();
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, STATUS_PROTOCOL);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1); // next state: status
+    send_packet(&mut stream, 0x00, &handshake)?;
+    send_packet(&mut stream, 0x00, &[])?;
+
+    let (id, data) = read_packet(&mut stream)?;
+    if id != 0x00 {
+        return Err(anyhow!("Unexpected packet {:#04x} instead of the status response", id));
+    }
+    let mut reader = data.as_slice();
+    let length = read_varint(&mut reader)? as usize;
+    let json = reader.get(..length).ok_or_else(|| anyhow!("Truncated status response"))?;
+    let status: Value = serde_json::from_slice(json).context("Invalid status JSON from the server")?;
+    let mut latency = started.elapsed();
+
+    // The ping round trip is the better latency measure; servers that close the
+    // connection after the status keep the status round trip instead
+    let payload = started.elapsed().as_millis() as i64;
+    let pinged = Instant::now();
+    if send_packet(&mut stream, 0x01, &payload.to_be_bytes()).is_ok()
+        && let Ok((0x01, pong)) = read_packet(&mut stream)
+        && pong == payload.to_be_bytes()
+    {
+        latency = pinged.elapsed();
+    }
+
+    let players = &status["players"];
+    Ok(ServerStatus {
+        version: chat::strip_formatting(status["version"]["name"].as_str().unwrap_or_default()),
+        protocol: status["version"]["protocol"].as_i64().unwrap_or_default(),
+        online: players["online"].as_u64().unwrap_or_default(),
+        max: players["max"].as_u64().unwrap_or_default(),
+        players: players["sample"]
+            .as_array()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .filter_map(|p| p["name"].as_str())
+                    .map(chat::strip_formatting)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        motd: chat::plain_text(&status["description"]),
+        latency_ms: latency.as_millis() as u64,
+        legacy: false,
+    })
+}
+
+fn utf16_be(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect()
+}
+
+// The 1.6 ping (0xFE 0x01 plus MC|PingHost), which 1.4-1.6 servers answer and older
+// ones still reply to with the "motd§online§max" format
+fn ping_legacy(host: &str, port: u16) -> Result<ServerStatus> {
+    let mut stream = connect(host, port)?;
+    let started = Instant::now();
+
+    let channel = "MC|PingHost";
+    let mut data = vec![LEGACY_PROTOCOL];
+    data.extend_from_slice(&(host.encode_utf16().count() as u16).to_be_bytes());
+    data.extend_from_slice(&utf16_be(host));
+    data.extend_from_slice(&(port as i32).to_be_bytes());
+
+    let mut packet = vec![0xfe, 0x01, 0xfa];
+    packet.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&utf16_be(channel));
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&data);
+    stream.write_all(&packet)?;
+
+    let mut header = [0; 3];
+    stream.read_exact(&mut header).context("Connection closed by the server")?;
+    if header[0] != 0xff {
+        return Err(anyhow!("Not a Minecraft server (unexpected reply {:#04x})", header[0]));
+    }
+    let length = u16::from_be_bytes([header[1], header[2]]) as usize;
+    let mut body = vec![0; length * 2];
+    stream.read_exact(&mut body).context("Truncated legacy ping response")?;
+    let latency = started.elapsed();
+
+    let units: Vec<u16> = body.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+    let text = String::from_utf16_lossy(&units);
+
+    let (protocol, version, motd, online, max) = match text.strip_prefix("§1\0") {
+        Some(rest) => {
+            let fields: Vec<&str> = rest.split('\0').collect();
+            match fields.as_slice() {
+                [protocol, version, motd, online, max] => (
+                    protocol.parse().unwrap_or_default(),
+                    version.to_string(),
+                    motd.to_string(),
+                    *online,
+                    *max,
+                ),
+                _ => return Err(anyhow!("Invalid legacy ping response")),
+            }
+        }
+        None => {
+            let fields: Vec<&str> = text.rsplitn(3, '§').collect();
+            match fields.as_slice() {
+                [max, online, motd] => (0, "Beta 1.8-1.3".to_string(), motd.to_string(), *online, *max),
+                _ => return Err(anyhow!("Invalid legacy ping response")),
+            }
+        }
+    };
+
+    Ok(ServerStatus {
+        version,
+        protocol,
+        online: online.parse().unwrap_or_default(),
+        max: max.parse().unwrap_or_default(),
+        players: Vec::new(),
+        motd: chat::strip_formatting(&motd),
+        latency_ms: latency.as_millis() as u64,
+        legacy: true,
+    })
+}
+
+// Tries the current protocol first, then the legacy ping for pre-1.7 servers
+pub fn ping(host: &str, port: u16) -> Result<ServerStatus> {
+    match ping_modern(host, port) {
+        Ok(status) => Ok(status),
+        Err(modern) => ping_legacy(host, port).map_err(|_| modern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Serves one connection on a free local port, handing it to `handle`
+    fn fake_server(handle: impl FnOnce(TcpListener) + Send + 'static) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || handle(listener));
+        port
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 255, 300, 25565, i32::MAX, -1, i32::MIN] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), value);
+        }
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300);
+        assert_eq!(buf, [0xac, 0x02]);
+
+        buf.clear();
+        write_varint(&mut buf, -1);
+        assert_eq!(buf, [0xff, 0xff, 0xff, 0xff, 0x0f]);
+
+        assert!(read_varint(&mut [0xff; 5].as_slice()).is_err());
+        assert!(read_varint(&mut [0x80].as_slice()).is_err());
+    }
+
+    #[test]
+    fn addresses() {
+        let parse = |address| parse_address(address, DEFAULT_PORT).unwrap();
+        assert_eq!(parse("example.com"), ("example.com".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("example.com:25570"), ("example.com".to_string(), 25570));
+        assert_eq!(parse("[::1]:25570"), ("::1".to_string(), 25570));
+        assert_eq!(parse("[::1]"), ("::1".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("::1"), ("::1".to_string(), DEFAULT_PORT));
+        assert_eq!(parse("2001:db8::1"), ("2001:db8::1".to_string(), DEFAULT_PORT));
+
+        assert!(parse_address("example.com:port", DEFAULT_PORT).is_err());
+        assert!(parse_address("example.com:70000", DEFAULT_PORT).is_err());
+        assert!(parse_address("[::1]25570", DEFAULT_PORT).is_err());
+        assert!(parse_address("[::1", DEFAULT_PORT).is_err());
+    }
+
+    #[test]
+    fn modern_ping() {
+        let port = fake_server(|listener| {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (id, handshake) = read_packet(&mut stream).unwrap();
+            assert_eq!(id, 0x00);
+            // Next state: status
+            assert_eq!(handshake.last(), Some(&1));
+            assert_eq!(read_packet(&mut stream).unwrap(), (0x00, Vec::new()));
+
+            let status = r#"{
+                "version": {"name": "Paper 1.21.1", "protocol": 767},
+                "players": {"max": 20, "online": 2, "sample": [{"name": "Notch", "id": ""}, {"name": "§cjeb_", "id": ""}]},
+                "description": {"text": "A ", "extra": [{"text": "§aMinecraft", "bold": true}, " Server"]}
+            }"#;
+            let mut data = Vec::new();
+            write_string(&mut data, status);
+            send_packet(&mut stream, 0x00, &data).unwrap();
+
+            let (id, payload) = read_packet(&mut stream).unwrap();
+            assert_eq!(id, 0x01);
+            send_packet(&mut stream, 0x01, &payload).unwrap();
+        });
+
+        let status = ping("127.0.0.1", port).unwrap();
+        assert_eq!(status.version, "Paper 1.21.1");
+        assert_eq!(status.protocol, 767);
+        assert_eq!((status.online, status.max), (2, 20));
+        assert_eq!(status.players, ["Notch", "jeb_"]);
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert!(!status.legacy);
+    }
+
+    // Answers the legacy ping and drops anything else, like a pre-1.7 server
+    // does with the modern handshake
+    fn legacy_server(reply: &'static str) -> u16 {
+        fake_server(move |listener| {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut header = [0; 3];
+                if stream.read_exact(&mut header).is_err() || header != [0xfe, 0x01, 0xfa] {
+                    continue;
+                }
+                // The MC|PingHost channel name and data, read in full before replying
+                let mut length = [0; 2];
+                stream.read_exact(&mut length).unwrap();
+                let mut rest = vec![0; u16::from_be_bytes(length) as usize * 2];
+                stream.read_exact(&mut rest).unwrap();
+                stream.read_exact(&mut length).unwrap();
+                let mut rest = vec![0; u16::from_be_bytes(length) as usize];
+                stream.read_exact(&mut rest).unwrap();
+                assert_eq!(rest[0], LEGACY_PROTOCOL);
+
+                let mut packet = vec![0xff];
+                packet.extend_from_slice(&(reply.encode_utf16().count() as u16).to_be_bytes());
+                packet.extend_from_slice(&utf16_be(reply));
+                stream.write_all(&packet).unwrap();
+                return;
+            }
+        })
+    }
+
+    #[test]
+    fn legacy_ping() {
+        let port = legacy_server("§1\u{0}74\u{0}1.6.4\u{0}A §aMinecraft Server\u{0}3\u{0}20");
+        let status = ping("127.0.0.1", port).unwrap();
+        assert_eq!(status.version, "1.6.4");
+        assert_eq!(status.protocol, 74);
+        assert_eq!((status.online, status.max), (3, 20));
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert!(status.legacy);
+    }
+
+    #[test]
+    fn beta_ping() {
+        let port = legacy_server("A Minecraft Server§1§8");
+        let status = ping("127.0.0.1", port).unwrap();
+        assert_eq!(status.version, "Beta 1.8-1.3");
+        assert_eq!((status.online, status.max), (1, 8));
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert!(status.legacy);
+    }
+}
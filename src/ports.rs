@@ -0,0 +1,126 @@
+use anyhow::{Result, anyhow};
+use std::io::{ErrorKind, IsTerminal};
+use std::net::{TcpListener, UdpSocket};
+use std::path::Path;
+use crate::config::ServerConfig;
+use crate::prompt::prompt_for_port;
+use crate::{control, ping, properties, rcon, registry};
+
+#[derive(Clone, Copy)]
+struct Port {
+    key: &'static str,
+    port: u16,
+    // Query listens on UDP and can share a number with a TCP port
+    udp: bool,
+}
+
+// The ports a server listens on once started, from its server.properties
+fn ports(server_dir: &Path) -> Vec<Port> {
+    let props = properties::load(server_dir).unwrap_or_default();
+    let port = |key: &str, default: u16| props.get(key).and_then(|p| p.parse().ok()).unwrap_or(default);
+    let enabled = |key: &str| props.get(key).map(String::as_str) == Some("true");
+
+    let server_port = port("server-port", ping::DEFAULT_PORT);
+    let mut ports = vec![Port { key: "server-port", port: server_port, udp: false }];
+    if enabled("enable-rcon") {
+        ports.push(Port { key: "rcon.port", port: port("rcon.port", rcon::DEFAULT_PORT), udp: false });
+    }
+    if enabled("enable-query") {
+        ports.push(Port { key: "query.port", port: port("query.port", server_port), udp: true });
+    }
+    ports
+}
+
+// Only a failed bind because of another listener counts; ports below 1024 fail
+// for other reasons when not running as root
+fn in_use_on_host(port: u16, udp: bool) -> bool {
+    let result = if udp {
+        UdpSocket::bind(("0.0.0.0", port)).map(drop)
+    } else {
+        TcpListener::bind(("0.0.0.0", port)).map(drop)
+    };
+    matches!(result, Err(e) if e.kind() == ErrorKind::AddrInUse)
+}
+
+// Who already has the port: another registered server, an earlier port of this
+// server, or some other program on the host
+fn owner(port: u16, udp: bool, taken: &[(String, Port)], check_host: bool) -> Option<String> {
+    if let Some((owner, _)) = taken.iter().find(|(_, p)| p.port == port && p.udp == udp) {
+        return Some(owner.clone());
+    }
+    if check_host && in_use_on_host(port, udp) {
+        return Some("another program on this machine".to_string());
+    }
+    None
+}
+
+fn next_free(port: &Port, taken: &[(String, Port)], check_host: bool) -> Option<u16> {
+    (port.port.saturating_add(1)..=u16::MAX).find(|&p| owner(p, port.udp, taken, check_host).is_none())
+}
+
+// Asks before moving a port; without a terminal to ask on, only says how to
+fn confirm_port(key: &str, free: u16) -> Result<bool> {
+    if std::io::stdin().is_terminal() {
+        return prompt_for_port(key, free);
+    }
+    println!("→ Run 'mcs props set {} {}' to use the next free port", key, free);
+    Ok(false)
+}
+
+// Checks server-port, rcon.port and query.port against the other servers in the
+// registry and the ports in use on this machine, offering the next free port for
+// each one that clashes
+pub fn check(server_dir: &Path, config: &ServerConfig) -> Result<()> {
+    let this = registry::find_by_path(server_dir)?.map(|e| e.name);
+
+    let mut taken = Vec::new();
+    for entry in registry::list()? {
+        if Some(&entry.name) == this.as_ref() || !entry.path.exists() {
+            continue;
+        }
+        for port in ports(&entry.path) {
+            taken.push((format!("server '{}' ({})", entry.name, port.key), port));
+        }
+    }
+
+    // A running server holds its own ports, which isn't a conflict
+    let check_host = !control::is_running(server_dir);
+
+    let mut changes = Vec::new();
+    for mut port in ports(server_dir) {
+        if let Some(owner) = owner(port.port, port.udp, &taken, check_host) {
+            println!("⚠ {} {} is already used by {}", port.key, port.port, owner);
+            let free = next_free(&port, &taken, check_host)
+                .ok_or_else(|| anyhow!("No free port above {} for {}", port.port, port.key))?;
+
+            if confirm_port(port.key, free)? {
+                port.port = free;
+                changes.push((port.key, free.to_string()));
+            } else {
+                println!("→ The server won't start while {} is taken", port.key);
+            }
+        }
+        taken.push((format!("this server's {}", port.key), port));
+    }
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+    properties::update(server_dir, &changes)?;
+
+    // Ports pinned in [properties] would be put back by the next 'mcs apply'
+    if changes.iter().any(|(key, _)| config.properties.contains_key(*key)) {
+        let mut config = config.clone();
+        for (key, value) in &changes {
+            if config.properties.contains_key(*key) {
+                config.properties.insert(key.to_string(), properties::to_toml(key, value));
+            }
+        }
+        config.save(server_dir)?;
+    }
+
+    for (key, value) in &changes {
+        println!("✓ Set {} = {}", key, value);
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use inquire::validator::Validation;
 use inquire::{Confirm, Select, Text};
-use crate::config::{ServerConfig, ServerType};
+use crate::config::{FlagPreset, ServerConfig, ServerType};
 use crate::{paper, vanilla, fabric, spigot, forge};
+use crate::units::MemorySize;
 
 pub fn prompt_for_config() -> Result<ServerConfig> {
     println!("▶ Minecraft Server Configuration\n");
@@ -77,19 +79,59 @@ pub fn prompt_for_config() -> Result<ServerConfig> {
         }
     };
 
-    let memory = Text::new("Memory allocation:")
+    let memory: MemorySize = Text::new("Memory allocation:")
         .with_default("2G")
-        .with_help_message("e.g., 2G, 4G, 8G")
-        .prompt()?;
-
-    let recommended_flags = if server_type == ServerType::Paper {
-        Confirm::new("Use Paper's recommended JVM flags?")
-            .with_default(true)
-            .with_help_message("Fetches recommended flags from the Paper API")
-            .prompt()?
-    } else {
-        false
+        .with_help_message("e.g., 2G, 4G, 8G, or 50% of system memory")
+        .with_validator(|input: &str| {
+            Ok(match input.parse::<MemorySize>() {
+                Ok(_) => Validation::Valid,
+                Err(e) => Validation::Invalid(e.into()),
+            })
+        })
+        .prompt()?
+        .parse()
+        .map_err(|e: String| anyhow!(e))?;
+
+    let flag_options = vec![
+        "Aikar's flags (G1GC, tuned for Minecraft)",
+        "Paper's recommended flags (from the Paper API)",
+        "ZGC (low pause times, needs plenty of memory)",
+        "None",
+    ];
+    let default_flags = match server_type {
+        ServerType::Paper => 1,
+        _ => 0,
+    };
+    let flags = match Select::new("JVM flags:", flag_options)
+        .with_starting_cursor(default_flags)
+        .prompt()?
+    {
+        f if f.starts_with("Aikar") => FlagPreset::Aikar,
+        f if f.starts_with("Paper") => FlagPreset::PaperApi,
+        f if f.starts_with("ZGC") => FlagPreset::Zgc,
+        _ => FlagPreset::None,
     };
 
-    Ok(ServerConfig::new(version, server_type, memory, recommended_flags))
+    Ok(ServerConfig::new(version, server_type, memory, flags))
+}
+
+pub fn prompt_for_rcon(enabled: bool) -> Result<bool> {
+    Ok(Confirm::new("Enable RCON?")
+        .with_default(enabled)
+        .with_help_message("Remote console used by 'mcs exec'; a password is generated for you")
+        .prompt()?)
+}
+
+pub fn prompt_for_query(enabled: bool) -> Result<bool> {
+    Ok(Confirm::new("Enable Query?")
+        .with_default(enabled)
+        .with_help_message("UDP status protocol used by 'mcs query' for the full player and plugin list")
+        .prompt()?)
+}
+
+pub fn prompt_for_port(key: &str, port: u16) -> Result<bool> {
+    Ok(Confirm::new(&format!("Use port {} for {} instead?", port, key))
+        .with_default(true)
+        .with_help_message("Written to server.properties")
+        .prompt()?)
 }
@@ -0,0 +1,459 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use crate::config::ServerConfig;
+
+pub const FILE: &str = "server.properties";
+// Minecraft writes this header (plus a timestamp) when it creates the file
+const HEADER: &str = "#Minecraft server properties";
+
+enum Kind {
+    Bool,
+    Int(i64, i64),
+    Enum(&'static [&'static str]),
+    String,
+}
+
+const PORT: Kind = Kind::Int(1, 65535);
+const DISTANCE: Kind = Kind::Int(3, 32);
+const PERMISSION_LEVEL: Kind = Kind::Int(0, 4);
+const UNBOUNDED: Kind = Kind::Int(0, i32::MAX as i64);
+
+// The properties of the vanilla dedicated server, 1.7 to current
+const KNOWN: &[(&str, Kind)] = &[
+    ("accepts-transfers", Kind::Bool),
+    ("allow-flight", Kind::Bool),
+    ("allow-nether", Kind::Bool),
+    ("broadcast-console-to-ops", Kind::Bool),
+    ("broadcast-rcon-to-ops", Kind::Bool),
+    ("bug-report-link", Kind::String),
+    // Older versions wrote the numeric ids
+    ("difficulty", Kind::Enum(&["peaceful", "easy", "normal", "hard", "0", "1", "2", "3"])),
+    ("enable-command-block", Kind::Bool),
+    ("enable-jmx-monitoring", Kind::Bool),
+    ("enable-query", Kind::Bool),
+    ("enable-rcon", Kind::Bool),
+    ("enable-status", Kind::Bool),
+    ("enforce-secure-profile", Kind::Bool),
+    ("enforce-whitelist", Kind::Bool),
+    ("entity-broadcast-range-percentage", Kind::Int(10, 1000)),
+    ("force-gamemode", Kind::Bool),
+    ("function-permission-level", Kind::Int(1, 4)),
+    ("gamemode", Kind::Enum(&["survival", "creative", "adventure", "spectator", "0", "1", "2", "3"])),
+    ("generate-structures", Kind::Bool),
+    ("generator-settings", Kind::String),
+    ("hardcore", Kind::Bool),
+    ("hide-online-players", Kind::Bool),
+    ("initial-disabled-packs", Kind::String),
+    ("initial-enabled-packs", Kind::String),
+    ("level-name", Kind::String),
+    ("level-seed", Kind::String),
+    ("level-type", Kind::String),
+    ("log-ips", Kind::Bool),
+    ("max-build-height", Kind::Int(64, 256)),
+    ("max-chained-neighbor-updates", Kind::Int(-1, i32::MAX as i64)),
+    ("max-players", UNBOUNDED),
+    ("max-tick-time", Kind::Int(-1, i64::MAX)),
+    ("max-world-size", Kind::Int(1, 29999984)),
+    ("motd", Kind::String),
+    ("network-compression-threshold", Kind::Int(-1, i32::MAX as i64)),
+    ("online-mode", Kind::Bool),
+    ("op-permission-level", PERMISSION_LEVEL),
+    ("pause-when-empty-seconds", UNBOUNDED),
+    ("player-idle-timeout", UNBOUNDED),
+    ("prevent-proxy-connections", Kind::Bool),
+    ("previews-chat", Kind::Bool),
+    ("pvp", Kind::Bool),
+    ("query.port", PORT),
+    ("rate-limit", UNBOUNDED),
+    ("rcon.password", Kind::String),
+    ("rcon.port", PORT),
+    ("region-file-compression", Kind::Enum(&["deflate", "lz4", "none"])),
+    ("require-resource-pack", Kind::Bool),
+    ("resource-pack", Kind::String),
+    ("resource-pack-id", Kind::String),
+    ("resource-pack-prompt", Kind::String),
+    ("resource-pack-sha1", Kind::String),
+    ("server-ip", Kind::String),
+    ("server-port", PORT),
+    ("simulation-distance", DISTANCE),
+    ("snooper-enabled", Kind::Bool),
+    ("spawn-animals", Kind::Bool),
+    ("spawn-monsters", Kind::Bool),
+    ("spawn-npcs", Kind::Bool),
+    ("spawn-protection", UNBOUNDED),
+    ("sync-chunk-writes", Kind::Bool),
+    ("text-filtering-config", Kind::String),
+    ("text-filtering-version", UNBOUNDED),
+    ("use-native-transport", Kind::Bool),
+    ("view-distance", DISTANCE),
+    ("white-list", Kind::Bool),
+];
+
+fn kind(key: &str) -> Option<&'static Kind> {
+    KNOWN.iter().find(|(k, _)| *k == key).map(|(_, kind)| kind)
+}
+
+pub fn is_known(key: &str) -> bool {
+    kind(key).is_some()
+}
+
+// Checks a value against the known type of its key; unknown keys pass
+pub fn validate(key: &str, value: &str) -> Result<()> {
+    let Some(kind) = kind(key) else {
+        return Ok(());
+    };
+    match kind {
+        Kind::Bool if value != "true" && value != "false" => {
+            Err(anyhow!("{} must be true or false, not '{}'", key, value))
+        }
+        Kind::Int(min, max) => match value.parse::<i64>() {
+            Ok(n) if (*min..=*max).contains(&n) => Ok(()),
+            Ok(_) if *max == i64::MAX => Err(anyhow!("{} must be at least {}, not {}", key, min, value)),
+            Ok(_) => Err(anyhow!("{} must be between {} and {}, not {}", key, min, max, value)),
+            Err(_) => Err(anyhow!("{} must be a whole number, not '{}'", key, value)),
+        },
+        Kind::Enum(values) if !values.contains(&value) => {
+            // The numeric ids are accepted but not advertised
+            let names: Vec<&str> = values.iter().copied().filter(|v| v.parse::<u32>().is_err()).collect();
+            Err(anyhow!("{} must be one of {}, not '{}'", key, names.join(", "), value))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The string server.properties stores for a value from mcs.toml's [properties]
+pub fn to_property(key: &str, value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => Err(anyhow!("properties.{} must be a string, number or boolean", key)),
+    }
+}
+
+// The reverse: typed where the key's type is known, so mcs.toml reads naturally
+pub fn to_toml(key: &str, value: &str) -> toml::Value {
+    match kind(key) {
+        Some(Kind::Bool) => value
+            .parse()
+            .map(toml::Value::Boolean)
+            .unwrap_or_else(|_| toml::Value::String(value.to_string())),
+        Some(Kind::Int(..)) => value
+            .parse()
+            .map(toml::Value::Integer)
+            .unwrap_or_else(|_| toml::Value::String(value.to_string())),
+        _ => toml::Value::String(value.to_string()),
+    }
+}
+
+// Fails on invalid values in mcs.toml's [properties]
+pub fn check_config(properties: &BTreeMap<String, toml::Value>) -> Result<()> {
+    for (key, value) in properties {
+        validate(key, &to_property(key, value)?).context("Invalid [properties] in mcs.toml")?;
+    }
+    Ok(())
+}
+
+// Writes mcs.toml's [properties] into server.properties
+pub fn apply(server_dir: &Path, config: &ServerConfig) -> Result<()> {
+    if config.properties.is_empty() {
+        return Ok(());
+    }
+
+    let mut values = Vec::new();
+    for (key, value) in &config.properties {
+        let value = to_property(key, value)?;
+        validate(key, &value)?;
+        if !is_known(key) {
+            println!("⚠ Unknown server property '{}'; writing it anyway", key);
+        }
+        values.push((key.as_str(), value));
+    }
+
+    let current = load(server_dir)?;
+    let changed = values
+        .iter()
+        .filter(|(key, value)| current.get(*key) != Some(value))
+        .count();
+    update(server_dir, &values)?;
+
+    if changed > 0 {
+        println!("✓ Updated {} server properties", changed);
+    }
+    Ok(())
+}
+
+// server.properties is a Java properties file: "key=value" lines, "#" or "!" comments,
+// and backslash escapes for separators and non-ASCII characters
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    // \uXXXX escapes are UTF-16, so a character outside the BMP takes two
+    let mut units = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('f') => '\x0c',
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    if let Ok(unit) = u16::from_str_radix(&hex, 16) {
+                        units.push(unit);
+                    }
+                    continue;
+                }
+                Some(other) => other,
+                None => continue,
+            },
+            c => c,
+        };
+        out.extend(char::decode_utf16(units.drain(..)).map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER)));
+        out.push(c);
+    }
+    out.extend(char::decode_utf16(units).map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER)));
+    out
+}
+
+fn escape(s: &str, key: bool) -> String {
+    let mut out = String::new();
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '=' | ':' | '#' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' ' if key || i == 0 => out.push_str("\\ "),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if !c.is_ascii() || c.is_ascii_control() => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Splits a (joined) line at the first unescaped '=', ':' or whitespace
+fn parse_line(line: &str) -> Option<(String, String)> {
+    let line = line.trim_start();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return None;
+    }
+
+    let mut escaped = false;
+    let mut split = line.len();
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            split = i;
+            break;
+        }
+    }
+
+    let key = unescape(&line[..split]);
+    let rest = line[split..].trim_start();
+    let rest = rest
+        .strip_prefix('=')
+        .or_else(|| rest.strip_prefix(':'))
+        .unwrap_or(rest)
+        .trim_start();
+    Some((key, unescape(rest)))
+}
+
+// Logical lines: a line ending in an odd number of backslashes continues on the next,
+// except for comments, which always end at the line break
+fn logical_lines(contents: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current: Option<String> = None;
+    for raw in contents.lines() {
+        if current.is_none() && raw.trim_start().starts_with(['#', '!']) {
+            lines.push(raw.to_string());
+            continue;
+        }
+        let part = match &current {
+            Some(_) => raw.trim_start(),
+            None => raw,
+        };
+        let trailing = part.len() - part.trim_end_matches('\\').len();
+        let (text, continues) = if trailing % 2 == 1 {
+            (&part[..part.len() - 1], true)
+        } else {
+            (part, false)
+        };
+        let mut line = current.take().unwrap_or_default();
+        line.push_str(text);
+        if continues {
+            current = Some(line);
+        } else {
+            lines.push(line);
+        }
+    }
+    lines.extend(current);
+    lines
+}
+
+pub fn parse(contents: &str) -> BTreeMap<String, String> {
+    logical_lines(contents)
+        .iter()
+        .filter_map(|line| parse_line(line))
+        .collect()
+}
+
+// The file's contents, empty when the server hasn't created it yet. Java reads and
+// writes properties files as ISO-8859-1, so every byte is one character.
+fn read(path: &Path) -> Result<String> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).context(format!("Failed to read {:?}", path)),
+    }
+}
+
+pub fn load(server_dir: &Path) -> Result<BTreeMap<String, String>> {
+    Ok(parse(&read(&server_dir.join(FILE))?))
+}
+
+// Sets the given keys in place, leaving comments, order and every other line alone.
+// Keys the file doesn't have yet are appended.
+pub fn update(server_dir: &Path, values: &[(&str, String)]) -> Result<()> {
+    let path = server_dir.join(FILE);
+    let contents = read(&path)?;
+
+    let mut found = vec![false; values.len()];
+    let mut lines = Vec::new();
+    for line in logical_lines(&contents) {
+        let key = parse_line(&line).map(|(key, _)| key);
+        // Every occurrence, since the last one of a duplicated key wins
+        match values.iter().position(|(k, _)| Some(*k) == key.as_deref()) {
+            Some(index) => {
+                let (key, value) = &values[index];
+                lines.push(format!("{}={}", escape(key, true), escape(value, false)));
+                found[index] = true;
+            }
+            None => lines.push(line),
+        }
+    }
+    for ((key, value), found) in values.iter().zip(found) {
+        if !found {
+            lines.push(format!("{}={}", escape(key, true), escape(value, false)));
+        }
+    }
+
+    if contents.is_empty() {
+        lines.insert(0, HEADER.to_string());
+    }
+
+    let updated = lines.join("\n") + "\n";
+    if updated != contents {
+        // New values are escaped to ASCII, and kept lines came from read(), so
+        // every character fits in a byte
+        let bytes: Vec<u8> = updated.chars().map(|c| c as u8).collect();
+        fs::write(&path, bytes).context(format!("Failed to write {:?}", path))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn separators_and_escapes() {
+        let props = parse("a=1\nb:2\nc 3\n  d  =  4\nkey\\ with\\:colon = x\\=y\\\\z\ntabs=\\ta\\nb\n");
+        assert_eq!(props["a"], "1");
+        assert_eq!(props["b"], "2");
+        assert_eq!(props["c"], "3");
+        assert_eq!(props["d"], "4");
+        assert_eq!(props["key with:colon"], "x=y\\z");
+        assert_eq!(props["tabs"], "\ta\nb");
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let props = parse("motd=caf\\u00e9 \\uD83D\\uDE00\n");
+        assert_eq!(props["motd"], "café 😀");
+
+        assert_eq!(escape("café 😀", false), "caf\\u00E9 \\uD83D\\uDE00");
+        assert_eq!(unescape(&escape("café 😀", false)), "café 😀");
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for (key, value) in [("a b", " leading space"), ("x=y", "a:b#c!d"), ("back\\slash", "tab\there\nnewline")] {
+            let line = format!("{}={}", escape(key, true), escape(value, false));
+            assert_eq!(parse_line(&line), Some((key.to_string(), value.to_string())));
+        }
+    }
+
+    #[test]
+    fn continuation_lines() {
+        let props = parse("motd=hello \\\n    world\nnext=\\\\\nlast=1\n");
+        assert_eq!(props["motd"], "hello world");
+        // An even number of backslashes is an escaped backslash, not a continuation
+        assert_eq!(props["next"], "\\");
+        assert_eq!(props["last"], "1");
+    }
+
+    #[test]
+    fn comments_never_continue() {
+        let props = parse("# a comment ending in a backslash \\\nmotd=x\n! another \\\nlevel-name=w\n");
+        assert_eq!(props["motd"], "x");
+        assert_eq!(props["level-name"], "w");
+        assert_eq!(props.len(), 2);
+    }
+
+    #[test]
+    fn last_duplicate_wins() {
+        assert_eq!(parse("pvp=true\npvp=false\n")["pvp"], "false");
+    }
+
+    #[test]
+    fn update_keeps_comments_and_order() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::write(
+            dir.join(FILE),
+            "#Minecraft server properties\n# keep me \\\npvp=true\nmotd=hi\n\npvp=true\nlevel-name=caf\u{e9}\n"
+                .chars()
+                .map(|c| c as u8)
+                .collect::<Vec<u8>>(),
+        )
+        .unwrap();
+
+        update(dir, &[("pvp", "false".to_string()), ("max-players", "5".to_string())]).unwrap();
+        let written = fs::read(dir.join(FILE)).unwrap();
+
+        assert_eq!(
+            written,
+            b"#Minecraft server properties\n# keep me \\\npvp=false\nmotd=hi\n\npvp=false\nlevel-name=caf\xe9\nmax-players=5\n"
+        );
+    }
+
+    #[test]
+    fn update_creates_the_file() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        update(dir, &[("motd", "café".to_string())]).unwrap();
+        let written = fs::read_to_string(dir.join(FILE)).unwrap();
+        let props = load(dir).unwrap();
+
+        assert_eq!(written, format!("{}\nmotd=caf\\u00E9\n", HEADER));
+        assert_eq!(props["motd"], "café");
+    }
+}
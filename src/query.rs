@@ -0,0 +1,248 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::time::Duration;
+use crate::ping;
+use crate::properties;
+
+// https://minecraft.wiki/w/Query
+const MAGIC: [u8; 2] = [0xfe, 0xfd];
+const TYPE_HANDSHAKE: u8 = 9;
+const TYPE_STAT: u8 = 0;
+const TIMEOUT: Duration = Duration::from_secs(5);
+// "splitnum\0\x80\0" before the key/value section
+const STAT_PADDING: usize = 11;
+// "\x01player_\0\0" between the key/values and the player list
+const PLAYERS_PADDING: usize = 10;
+
+pub struct QueryResult {
+    pub motd: String,
+    pub game_type: String,
+    pub version: String,
+    pub map: String,
+    pub online: u64,
+    pub max: u64,
+    // "Paper on 1.21.1: WorldEdit 7.3.4; LuckPerms 5.4.1" as reported by the server
+    pub plugins: String,
+    pub players: Vec<String>,
+}
+
+// Where this server answers queries, from its server.properties
+pub fn local_address(server_dir: &Path) -> Result<(String, u16)> {
+    let props = properties::load(server_dir)?;
+    if props.get("enable-query").map(String::as_str) != Some("true") {
+        return Err(anyhow!("Query is disabled for this server. Run 'mcs configure' to enable it."));
+    }
+
+    let (host, server_port) = ping::local_address(server_dir)?;
+    let port = props
+        .get("query.port")
+        .map(|p| p.parse::<u16>().context(format!("Invalid query.port '{}' in server.properties", p)))
+        .transpose()?
+        .unwrap_or(server_port);
+    Ok((host, port))
+}
+
+fn request(socket: &UdpSocket, kind: u8, session: i32, payload: &[u8]) -> Result<Vec<u8>> {
+    let mut packet = MAGIC.to_vec();
+    packet.push(kind);
+    packet.extend_from_slice(&session.to_be_bytes());
+    packet.extend_from_slice(payload);
+    socket.send(&packet)?;
+
+    let mut buf = vec![0; 65535];
+    let length = socket.recv(&mut buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+            anyhow!("No answer to the query. Is enable-query=true and the query port reachable over UDP?")
+        }
+        _ => anyhow!(e).context("Query failed (is the server running with enable-query=true?)"),
+    })?;
+    buf.truncate(length);
+
+    if buf.len() < 5 || buf[0] != kind || buf[1..5] != session.to_be_bytes() {
+        return Err(anyhow!("Unexpected reply to the query"));
+    }
+    Ok(buf.split_off(5))
+}
+
+// Splits off the next null-terminated string
+fn next_string(data: &mut &[u8]) -> Option<String> {
+    let end = data.iter().position(|&b| b == 0)?;
+    let s = String::from_utf8_lossy(&data[..end]).into_owned();
+    *data = &data[end + 1..];
+    Some(s)
+}
+
+// A socket bound to one of the host's addresses, with the challenge token it was
+// given; like a TCP connect, each address is tried in turn
+fn handshake(host: &str, port: u16, session: i32) -> Result<(UdpSocket, i32)> {
+    let addresses: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .context(format!("Failed to resolve {}", host))?
+        .collect();
+
+    let mut last_error = None;
+    for address in addresses {
+        let attempt = || -> Result<(UdpSocket, i32)> {
+            let socket = UdpSocket::bind(if address.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" })?;
+            socket.connect(address)?;
+            socket.set_read_timeout(Some(TIMEOUT))?;
+
+            let handshake = request(&socket, TYPE_HANDSHAKE, session, &[])?;
+            let challenge = next_string(&mut handshake.as_slice())
+                .and_then(|token| token.trim().parse().ok())
+                .ok_or_else(|| anyhow!("Invalid challenge token from the server"))?;
+            Ok((socket, challenge))
+        };
+        match attempt() {
+            Ok(found) => return Ok(found),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("{} has no addresses", host)))
+}
+
+pub fn query(host: &str, port: u16) -> Result<QueryResult> {
+    // Minecraft only looks at the lower 4 bits of each byte
+    let session = std::process::id() as i32 & 0x0f0f0f0f;
+    let (socket, challenge) = handshake(host, port, session)?;
+
+    let mut payload = challenge.to_be_bytes().to_vec();
+    // Asking for the full stat instead of the basic one
+    payload.extend_from_slice(&[0, 0, 0, 0]);
+    let stat = request(&socket, TYPE_STAT, session, &payload)?;
+
+    let mut data = stat
+        .get(STAT_PADDING..)
+        .ok_or_else(|| anyhow!("Truncated query response"))?;
+    let mut values = BTreeMap::new();
+    while let Some(key) = next_string(&mut data) {
+        if key.is_empty() {
+            break;
+        }
+        let value = next_string(&mut data).unwrap_or_default();
+        values.insert(key, value);
+    }
+
+    let mut players = Vec::new();
+    if let Some(mut rest) = data.get(PLAYERS_PADDING..) {
+        while let Some(name) = next_string(&mut rest) {
+            if name.is_empty() {
+                break;
+            }
+            players.push(name);
+        }
+    }
+
+    let mut take = |key: &str| values.remove(key).unwrap_or_default();
+    Ok(QueryResult {
+        motd: take("hostname"),
+        game_type: take("gametype"),
+        version: take("version"),
+        map: take("map"),
+        online: take("numplayers").parse().unwrap_or_default(),
+        max: take("maxplayers").parse().unwrap_or_default(),
+        plugins: take("plugins"),
+        players,
+    })
+}
+
+// Turns the query listener on or off in server.properties
+pub fn configure(server_dir: &Path, enable: bool) -> Result<()> {
+    let props = properties::load(server_dir)?;
+    let enabled = props.get("enable-query").map(String::as_str) == Some("true");
+
+    if enabled == enable {
+        return Ok(());
+    }
+    if !enable {
+        properties::update(server_dir, &[("enable-query", "false".to_string())])?;
+        println!("✓ Disabled Query");
+        return Ok(());
+    }
+
+    // Minecraft defaults query.port to the game port; UDP and TCP can share it
+    let port = match props.get("query.port") {
+        Some(port) => port.clone(),
+        None => ping::local_address(server_dir)?.1.to_string(),
+    };
+    properties::update(
+        server_dir,
+        &[("enable-query", "true".to_string()), ("query.port", port.clone())],
+    )?;
+
+    println!("✓ Enabled Query on UDP port {}", port);
+    println!("→ Restart the server for this to take effect");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const TOKEN: i32 = 9513307;
+    const FULL_STAT: &[u8] = b"splitnum\0\x80\0\
+        hostname\0A Minecraft Server\0gametype\0SMP\0game_id\0MINECRAFT\0version\x001.21.1\0\
+        plugins\0Paper on 1.21.1: WorldEdit 7.3.4\0map\0world\0numplayers\x002\0maxplayers\x0020\0\
+        hostport\x0025565\0hostip\x00127.0.0.1\0\0\
+        \x01player_\0\0Alex\0Steve\0\0";
+
+    // Answers like Minecraft's query listener: the handshake returns the token as
+    // text, and a stat request is only answered when it carries that token
+    fn fake_server(stat: &'static [u8]) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let mut buf = [0; 1500];
+            while let Ok((length, from)) = socket.recv_from(&mut buf) {
+                let packet = &buf[..length];
+                assert_eq!(packet[..2], MAGIC);
+                let (kind, session, payload) = (packet[2], &packet[3..7], &packet[7..]);
+
+                let mut reply = vec![kind];
+                reply.extend_from_slice(session);
+                match kind {
+                    TYPE_HANDSHAKE => reply.extend_from_slice(format!("{}\0", TOKEN).as_bytes()),
+                    TYPE_STAT if payload == [&TOKEN.to_be_bytes()[..], &[0; 4]].concat() => {
+                        reply.extend_from_slice(stat)
+                    }
+                    // Wrong token or a basic stat: answer with something query() rejects
+                    _ => reply[0] = 0xff,
+                }
+                socket.send_to(&reply, from).unwrap();
+            }
+        });
+
+        port
+    }
+
+    #[test]
+    fn queries_full_stat() {
+        let port = fake_server(FULL_STAT);
+        let result = query("127.0.0.1", port).unwrap();
+
+        assert_eq!(result.motd, "A Minecraft Server");
+        assert_eq!(result.game_type, "SMP");
+        assert_eq!(result.version, "1.21.1");
+        assert_eq!(result.map, "world");
+        assert_eq!((result.online, result.max), (2, 20));
+        assert_eq!(result.plugins, "Paper on 1.21.1: WorldEdit 7.3.4");
+        assert_eq!(result.players, ["Alex", "Steve"]);
+    }
+
+    #[test]
+    fn truncated_replies() {
+        let port = fake_server(b"splitnum\0");
+        let err = query("127.0.0.1", port).err().unwrap();
+        assert!(err.to_string().contains("Truncated"));
+
+        // Cut off before the player list: the key/values still come through
+        let port = fake_server(b"splitnum\0\x80\0version\x001.21.1\0\0");
+        let result = query("127.0.0.1", port).unwrap();
+        assert_eq!(result.version, "1.21.1");
+        assert!(result.players.is_empty());
+    }
+}
@@ -0,0 +1,361 @@
+use anyhow::{Context, Result, anyhow};
+use std::io::{self, BufRead, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+use crate::chat;
+use crate::properties;
+
+// https://developer.valvesoftware.com/wiki/Source_RCON_Protocol
+const TYPE_RESPONSE: i32 = 0;
+const TYPE_COMMAND: i32 = 2;
+const TYPE_AUTH_RESPONSE: i32 = 2;
+const TYPE_LOGIN: i32 = 3;
+
+pub const DEFAULT_PORT: u16 = 25575;
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Minecraft drops commands longer than this
+const MAX_COMMAND_LENGTH: usize = 1446;
+const MAX_PACKET_LENGTH: i32 = 1024 * 1024;
+
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: String,
+}
+
+pub struct Rcon {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl Rcon {
+    pub fn connect(host: &str, port: u16, password: &str) -> Result<Self> {
+        let stream = TcpStream::connect((host, port)).context(format!(
+            "Failed to connect to RCON at {} (is the server running?)",
+            display_address(host, port)
+        ))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut rcon = Self { stream, next_id: 1 };
+        let id = rcon.next_id();
+        rcon.write_packet(id, TYPE_LOGIN, password)?;
+
+        // Source servers send an empty response before the auth result; Minecraft doesn't
+        loop {
+            let packet = rcon.read_packet()?;
+            if packet.kind != TYPE_AUTH_RESPONSE {
+                continue;
+            }
+            if packet.id == -1 {
+                return Err(anyhow!("RCON login failed: wrong password"));
+            }
+            return Ok(rcon);
+        }
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        id
+    }
+
+    fn write_packet(&mut self, id: i32, kind: i32, body: &str) -> Result<()> {
+        let length = 4 + 4 + body.len() + 2;
+        let mut packet = Vec::with_capacity(4 + length);
+        packet.extend_from_slice(&(length as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet).context("Failed to send to RCON")?;
+        Ok(())
+    }
+
+    fn read_packet(&mut self) -> Result<Packet> {
+        let length = read_i32(&mut self.stream)?;
+        if !(10..=MAX_PACKET_LENGTH).contains(&length) {
+            return Err(anyhow!("Invalid RCON packet length {}", length));
+        }
+        let id = read_i32(&mut self.stream)?;
+        let kind = read_i32(&mut self.stream)?;
+        let mut body = vec![0; length as usize - 8];
+        self.stream.read_exact(&mut body).context("RCON connection closed")?;
+        // Body and packet terminators
+        body.truncate(body.len() - 2);
+
+        Ok(Packet {
+            id,
+            kind,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    // Runs a command and returns its output. Long output arrives split over several
+    // packets, so an invalid request is sent right after: its reply marks the end.
+    pub fn exec(&mut self, command: &str) -> Result<String> {
+        if command.len() > MAX_COMMAND_LENGTH {
+            return Err(anyhow!("Command is too long for RCON (at most {} bytes)", MAX_COMMAND_LENGTH));
+        }
+
+        let id = self.next_id();
+        let end_id = self.next_id();
+        self.write_packet(id, TYPE_COMMAND, command)?;
+        self.write_packet(end_id, TYPE_RESPONSE, "")?;
+
+        let mut output = String::new();
+        loop {
+            let packet = self.read_packet()?;
+            if packet.id == end_id {
+                return Ok(output);
+            }
+            if packet.id == -1 {
+                return Err(anyhow!("RCON session is no longer authenticated"));
+            }
+            if packet.id == id {
+                output.push_str(&packet.body);
+            }
+        }
+    }
+}
+
+fn read_i32(stream: &mut impl Read) -> Result<i32> {
+    let mut bytes = [0; 4];
+    stream.read_exact(&mut bytes).context("RCON connection closed")?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+// host:port, with IPv6 hosts in brackets
+pub fn display_address(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// Host, port and password of a local server, from its server.properties
+pub fn local_settings(server_dir: &Path) -> Result<(String, u16, String)> {
+    let props = properties::load(server_dir)?;
+
+    if props.get("enable-rcon").map(String::as_str) != Some("true") {
+        return Err(anyhow!("RCON is disabled for this server. Run 'mcs configure' to enable it."));
+    }
+    let password = props
+        .get("rcon.password")
+        .filter(|p| !p.is_empty())
+        .cloned()
+        .ok_or_else(|| anyhow!("rcon.password is empty in server.properties. Run 'mcs configure' to set one."))?;
+    let port = props
+        .get("rcon.port")
+        .map(|p| p.parse::<u16>().context(format!("Invalid rcon.port '{}' in server.properties", p)))
+        .transpose()?
+        .unwrap_or(DEFAULT_PORT);
+    let host = props
+        .get("server-ip")
+        .filter(|ip| !ip.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+
+    Ok((host, port, password))
+}
+
+// 32 characters from a 64-character alphabet: 192 bits of randomness
+pub fn generate_password() -> Result<String> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("Failed to generate a password: {}", e))?;
+    Ok(bytes
+        .iter()
+        .map(|b| ALPHABET[(b % 64) as usize] as char)
+        .collect())
+}
+
+// Turns RCON on or off in server.properties, generating a password when there is none
+pub fn configure(server_dir: &Path, enable: bool) -> Result<()> {
+    let props = properties::load(server_dir)?;
+    let enabled = props.get("enable-rcon").map(String::as_str) == Some("true");
+    let has_password = props.get("rcon.password").is_some_and(|p| !p.is_empty());
+
+    if !enable {
+        if enabled {
+            properties::update(server_dir, &[("enable-rcon", "false".to_string())])?;
+            println!("✓ Disabled RCON");
+        }
+        return Ok(());
+    }
+    if enabled && has_password {
+        return Ok(());
+    }
+
+    let port = props
+        .get("rcon.port")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_PORT.to_string());
+    let mut values = vec![("enable-rcon", "true".to_string()), ("rcon.port", port.clone())];
+    if !has_password {
+        values.push(("rcon.password", generate_password()?));
+    }
+    properties::update(server_dir, &values)?;
+
+    println!("✓ Enabled RCON on port {} (password in server.properties)", port);
+    println!("→ Restart the server for this to take effect");
+    Ok(())
+}
+
+// Reads commands line by line until end of input or "exit"
+pub fn repl(rcon: &mut Rcon) -> Result<()> {
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let command = line.trim();
+        match command {
+            "" => continue,
+            "exit" | "quit" => return Ok(()),
+            _ => {}
+        }
+
+        let output = rcon.exec(command)?;
+        if !output.is_empty() {
+            println!("{}", chat::strip_formatting(output.trim_end()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const PASSWORD: &str = "hunter2";
+
+    fn write(stream: &mut TcpStream, id: i32, kind: i32, body: &str) {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&((body.len() + 10) as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        stream.write_all(&packet).unwrap();
+    }
+
+    fn read(stream: &mut TcpStream) -> Option<(i32, i32, String)> {
+        let length = read_i32(stream).ok()?;
+        let id = read_i32(stream).ok()?;
+        let kind = read_i32(stream).ok()?;
+        let mut body = vec![0; length as usize - 8];
+        stream.read_exact(&mut body).ok()?;
+        body.truncate(body.len() - 2);
+        Some((id, kind, String::from_utf8(body).unwrap()))
+    }
+
+    // Behaves like Minecraft's RCON thread: replies in 4096-byte chunks and answers
+    // unknown request types with an error message under the same id
+    fn fake_server() -> u16 {
+        fake_server_on("127.0.0.1").unwrap()
+    }
+
+    fn fake_server_on(host: &str) -> Option<u16> {
+        let listener = TcpListener::bind((host, 0)).ok()?;
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                thread::spawn(move || {
+                    let mut authenticated = false;
+                    while let Some((id, kind, body)) = read(&mut stream) {
+                        match kind {
+                            TYPE_LOGIN if body == PASSWORD => {
+                                authenticated = true;
+                                write(&mut stream, id, TYPE_AUTH_RESPONSE, "");
+                            }
+                            TYPE_LOGIN => write(&mut stream, -1, TYPE_AUTH_RESPONSE, ""),
+                            TYPE_COMMAND if !authenticated => write(&mut stream, -1, TYPE_RESPONSE, ""),
+                            TYPE_COMMAND => {
+                                let output = match body.as_str() {
+                                    "list" => "There are §e0§r of a max of 20 players online: ".to_string(),
+                                    "long" => "x".repeat(10_000),
+                                    other => format!("Unknown command: {}", other),
+                                };
+                                let bytes = output.as_bytes();
+                                for chunk in bytes.chunks(4096) {
+                                    write(&mut stream, id, TYPE_RESPONSE, std::str::from_utf8(chunk).unwrap());
+                                }
+                                if bytes.is_empty() {
+                                    write(&mut stream, id, TYPE_RESPONSE, "");
+                                }
+                            }
+                            other => write(&mut stream, id, TYPE_RESPONSE, &format!("Unknown request {:x}", other)),
+                        }
+                    }
+                });
+            }
+        });
+
+        Some(port)
+    }
+
+    #[test]
+    fn executes_commands() {
+        let port = fake_server();
+        let mut rcon = Rcon::connect("127.0.0.1", port, PASSWORD).unwrap();
+
+        let output = rcon.exec("list").unwrap();
+        assert_eq!(output, "There are §e0§r of a max of 20 players online: ");
+        assert_eq!(chat::strip_formatting(&output), "There are 0 of a max of 20 players online: ");
+        assert_eq!(rcon.exec("foo").unwrap(), "Unknown command: foo");
+    }
+
+    #[test]
+    fn rejects_wrong_password() {
+        let port = fake_server();
+        let err = Rcon::connect("127.0.0.1", port, "wrong").err().unwrap();
+        assert!(err.to_string().contains("wrong password"));
+    }
+
+    #[test]
+    fn joins_split_responses() {
+        let port = fake_server();
+        let mut rcon = Rcon::connect("127.0.0.1", port, PASSWORD).unwrap();
+
+        assert_eq!(rcon.exec("long").unwrap(), "x".repeat(10_000));
+        // The connection stays in sync for the next command
+        assert_eq!(rcon.exec("foo").unwrap(), "Unknown command: foo");
+    }
+
+    #[test]
+    fn rejects_long_commands() {
+        let port = fake_server();
+        let mut rcon = Rcon::connect("127.0.0.1", port, PASSWORD).unwrap();
+        assert!(rcon.exec(&"a".repeat(MAX_COMMAND_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn connects_over_ipv6() {
+        // Not every machine has IPv6 loopback
+        let Some(port) = fake_server_on("::1") else {
+            return;
+        };
+        let mut rcon = Rcon::connect("::1", port, PASSWORD).unwrap();
+        assert_eq!(rcon.exec("foo").unwrap(), "Unknown command: foo");
+        assert_eq!(display_address("::1", port), format!("[::1]:{}", port));
+    }
+
+    #[test]
+    fn generates_distinct_passwords() {
+        let first = generate_password().unwrap();
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, generate_password().unwrap());
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::global;
+
+// ~/.mcs/servers/<name>.toml, one file per server that mcs has set up
+const DIR: &str = "servers";
+// list() runs several times per command; bad entries are reported once
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize)]
+struct EntryFile {
+    path: PathBuf,
+}
+
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+fn dir() -> Result<PathBuf> {
+    Ok(global::mcs_home()?.join(DIR))
+}
+
+// Compares paths the same way however they were typed
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn read_entry(path: &Path) -> Result<EntryFile> {
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+// The registered servers; an unreadable entry is skipped with a warning rather
+// than failing every command that looks at the registry
+pub fn list() -> Result<Vec<Entry>> {
+    let dir = dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for file in fs::read_dir(&dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = file?.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        match read_entry(&path) {
+            Ok(entry) => entries.push(Entry {
+                name: name.to_string(),
+                path: entry.path,
+            }),
+            Err(e) => {
+                let reason = e.to_string().lines().next().unwrap_or_default().to_string();
+                skipped.push(format!("{} ({})", path.display(), reason));
+            }
+        }
+    }
+    if !skipped.is_empty() && !WARNED.swap(true, Ordering::Relaxed) {
+        for entry in skipped {
+            println!("⚠ Skipping unreadable registry entry {}", entry);
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+pub fn find_by_path(server_dir: &Path) -> Result<Option<Entry>> {
+    let wanted = normalize(server_dir);
+    Ok(list()?.into_iter().find(|e| normalize(&e.path) == wanted))
+}
+
+// Resolves --server <name>
+pub fn find(name: &str) -> Result<Entry> {
+    let entry = list()?
+        .into_iter()
+        .find(|e| e.name == name)
+        .ok_or_else(|| anyhow!("No server named '{}'. Run 'mcs servers' to list registered servers.", name))?;
+    if !entry.path.exists() {
+        return Err(anyhow!("Server '{}' was registered at {}, which no longer exists", name, entry.path.display()));
+    }
+    Ok(entry)
+}
+
+// Server names come from the directory name, made safe for a file name
+fn base_name(server_dir: &Path) -> String {
+    let name: String = server_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() { "server".to_string() } else { name.to_string() }
+}
+
+// Adds a server to the registry unless it's already there, picking "name-2",
+// "name-3", ... when another server has the same directory name
+pub fn register(server_dir: &Path) -> Result<Entry> {
+    if let Some(entry) = find_by_path(server_dir)? {
+        return Ok(entry);
+    }
+
+    let dir = dir()?;
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+
+    let base = base_name(server_dir);
+    let mut name = base.clone();
+    let mut n = 2;
+    while dir.join(format!("{}.toml", name)).exists() {
+        name = format!("{}-{}", base, n);
+        n += 1;
+    }
+
+    let path = normalize(server_dir);
+    let contents = toml::to_string(&EntryFile { path: path.clone() })?;
+    fs::write(dir.join(format!("{}.toml", name)), contents)?;
+
+    println!("■ Registered this server as '{}'", name);
+    Ok(Entry { name, path })
+}
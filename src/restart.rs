@@ -0,0 +1,96 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use crate::config::ServerType;
+use crate::overlay;
+
+// Created by the server's /restart command (through spigot.yml's restart-script) so
+// the start script or `mcs run` can tell a requested restart from a /stop
+pub const MARKER: &str = ".mcs-restart";
+// Spigot runs restart-script from a shutdown hook without waiting for it, so the
+// marker can show up shortly after the JVM has exited
+pub const MARKER_WAIT: Duration = Duration::from_secs(2);
+
+// Spigot runs restart-script with `sh <script>` on Unix and `cmd /c start <script>`
+// on Windows, so one script that is valid for both works wherever the server runs
+const RESTART_SCRIPT: &str = "restart.cmd";
+// What spigot.yml ships with, put back when the restart policy is removed
+const DEFAULT_RESTART_SCRIPT: &str = "./start.sh";
+// Written by earlier versions of mcs, one per platform
+const OLD_RESTART_SCRIPTS: &[&str] = &["./restart.sh", "restart.bat"];
+const SETTING: &str = "settings.restart-script";
+
+// Servers whose /restart runs spigot.yml's restart-script
+pub fn uses_restart_script(server_type: &ServerType) -> bool {
+    matches!(server_type, ServerType::Paper | ServerType::Spigot)
+}
+
+// sh runs the first line (':' is a no-op and '#' hides the \r) and exits there;
+// cmd sees that line as a label and runs the rest
+fn write_marker_script(path: &Path) -> Result<()> {
+    fs::write(
+        path.join(RESTART_SCRIPT),
+        format!(
+            ":; touch {marker}; exit 0 #\r\n@echo off\r\nrem Run by /restart; the start script relaunches the server\r\ntype nul > {marker}\r\nexit\r\n",
+            marker = MARKER
+        ),
+    )?;
+    Ok(())
+}
+
+fn set_restart_script(path: &Path, script: &str) -> Result<bool> {
+    let mut settings = toml::Table::new();
+    settings.insert(SETTING.to_string(), toml::Value::String(script.to_string()));
+    Ok(overlay::merge(path, "spigot.yml", &settings)?.is_some())
+}
+
+// Points Spigot's restart-script at the marker script. By default it runs ./start.sh,
+// which would start a second server next to the restarting one. spigot.yml is
+// merged like a [files] overlay, so a missing file is created with just this setting
+// and Spigot fills in the rest on first start.
+pub fn configure_spigot(path: &Path) -> Result<()> {
+    write_marker_script(path)?;
+    if set_restart_script(path, RESTART_SCRIPT)? {
+        println!("✓ Set restart-script in spigot.yml");
+    }
+    Ok(())
+}
+
+// Without a restart policy, /restart should run the server's own start script again,
+// so a restart-script mcs set is put back to Spigot's default. Anything else is the
+// user's and left alone.
+pub fn reset_spigot(path: &Path) -> Result<()> {
+    let spigot = overlay::read(path, "spigot.yml")?;
+    let current = spigot["settings"]["restart-script"].as_str();
+    let ours = current.is_some_and(|s| s == RESTART_SCRIPT || OLD_RESTART_SCRIPTS.contains(&s));
+    if ours && set_restart_script(path, DEFAULT_RESTART_SCRIPT)? {
+        println!("✓ Reset restart-script in spigot.yml to {}", DEFAULT_RESTART_SCRIPT);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+
+    fn restart_script(path: &Path) -> String {
+        overlay::read(path, "spigot.yml").unwrap()["settings"]["restart-script"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn reset_only_touches_our_script() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+
+        configure_spigot(dir).unwrap();
+        assert_eq!(restart_script(dir), RESTART_SCRIPT);
+        reset_spigot(dir).unwrap();
+        assert_eq!(restart_script(dir), DEFAULT_RESTART_SCRIPT);
+
+        fs::write(dir.join("spigot.yml"), "settings:\n  restart-script: ./my-restart.sh\n").unwrap();
+        reset_spigot(dir).unwrap();
+        assert_eq!(restart_script(dir), "./my-restart.sh");
+    }
+}
@@ -1,39 +1,79 @@
 use anyhow::Result;
 use std::fs;
-use std::path::PathBuf;
-use crate::config::{ServerConfig, ServerType};
-use crate::paper;
-
-pub fn create_start_scripts(path: &PathBuf, config: &ServerConfig) -> Result<()> {
-    let mut flags = String::new();
-    if config.recommended_flags && config.server_type == ServerType::Paper {
-        match paper::get_recommended_flags(&config.version) {
-            Ok(f) if !f.is_empty() => {
-                flags = format!("{} ", f.join(" "));
-                println!("✓ Using recommended JVM flags for Paper {}", config.version);
-            }
-            _ => println!("⚠ Couldn't fetch recommended JVM flags, using defaults"),
-        }
+use std::path::Path;
+use crate::config::{RestartPolicy, ServerConfig};
+use crate::launch::{self, Launch, Target};
+use crate::restart;
+
+// Leaves simple arguments bare and single-quotes anything the shell would split or expand
+fn sh_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_=.,:/@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
     }
+}
 
-    let bat_content = format!(
-        r#"@echo off
-java -Xms{memory} -Xmx{memory} {flags}-jar server.jar nogui
-pause
-"#,
-        memory = config.memory,
-        flags = flags
-    );
+// cmd expands %VAR% everywhere, quoted or not, so '%' is always doubled. `bang` is
+// for a line that runs under EnableDelayedExpansion and contains a '!': cmd then
+// expands !VAR! and strips one level of carets, even inside quotes.
+fn bat_quote(arg: &str, bang: bool) -> String {
+    let mut escaped = arg.replace('%', "%%");
+    if bang {
+        escaped = escaped.replace('^', "^^").replace('!', "^!");
+    }
+    if !arg.is_empty() && !arg.contains([' ', '&', '|', '<', '>', '^', '!', '"']) {
+        escaped
+    } else {
+        format!("\"{}\"", escaped.replace('"', "\"\""))
+    }
+}
+
+// The java command line for start.bat; `delayed` when it runs inside the restart loop
+fn bat_command(java: &str, args: &[String], delayed: bool) -> String {
+    let bang = delayed && (java.contains('!') || args.iter().any(|a| a.contains('!')));
+    let mut command = bat_quote(java, bang);
+    for arg in args {
+        command.push(' ');
+        command.push_str(&bat_quote(arg, bang));
+    }
+    command
+}
+
+pub fn create_start_scripts(path: &Path, config: &ServerConfig) -> Result<()> {
+    let launch = launch::build(path, config)?;
+
+    if let Target::ArgFiles { .. } = launch.target {
+        write_user_jvm_args(path, &launch)?;
+    }
+
+    let wait_for_marker = restart::uses_restart_script(&config.server_type);
+
+    let mut bat_content = String::from("@echo off\n");
+    // Set before the restart loop turns on delayed expansion
+    for (key, value) in &launch.env {
+        bat_content.push_str(&format!("set \"{}={}\"\n", key, value.replace('%', "%%")));
+    }
+    let bat_command = bat_command(&launch.java, &launch.args(true), config.restart.is_some());
+    match &config.restart {
+        Some(policy) => bat_content.push_str(&bat_restart_loop(&bat_command, policy, wait_for_marker)?),
+        None => bat_content.push_str(&format!("{}\n", bat_command)),
+    }
+    bat_content.push_str("pause\n");
 
     fs::write(path.join("start.bat"), bat_content)?;
 
-    let sh_content = format!(
-        r#"#!/bin/bash
-java -Xms{memory} -Xmx{memory} {flags}-jar server.jar nogui
-"#,
-        memory = config.memory,
-        flags = flags
-    );
+    let mut sh_content = String::from("#!/bin/bash\n");
+    for (key, value) in &launch.env {
+        sh_content.push_str(&format!("export {}={}\n", key, sh_quote(value)));
+    }
+    let sh_args: Vec<String> = launch.args(false).iter().map(|a| sh_quote(a)).collect();
+    let sh_command = format!("{} {}", sh_quote(&launch.java), sh_args.join(" "));
+    match &config.restart {
+        Some(policy) => sh_content.push_str(&sh_restart_loop(&sh_command, policy, wait_for_marker)?),
+        None => sh_content.push_str(&format!("{}\n", sh_command)),
+    }
 
     fs::write(path.join("start.sh"), sh_content)?;
 
@@ -45,12 +85,170 @@ java -Xms{memory} -Xmx{memory} {flags}-jar server.jar nogui
         fs::set_permissions(path.join("start.sh"), perms)?;
     }
 
+    if wait_for_marker {
+        match config.restart {
+            Some(_) => restart::configure_spigot(path)?,
+            None => restart::reset_spigot(path)?,
+        }
+    }
+
     println!("✓ Created start scripts");
 
     Ok(())
 }
 
-pub fn create_eula(path: &PathBuf) -> Result<()> {
+// Relaunches after /restart (which leaves the marker file behind) and, with on_crash,
+// after a non-zero exit. More than max_restarts crashes within one window ends the
+// loop. A clean /stop (exit code 0) or Ctrl+C always ends it.
+// With `wait_for_marker`, a clean exit waits up to restart::MARKER_WAIT for the
+// marker before it counts as a /stop.
+fn sh_restart_loop(command: &str, policy: &RestartPolicy, wait_for_marker: bool) -> Result<String> {
+    let marker_wait = if wait_for_marker {
+        format!(
+            r#"    if [ $code -eq 0 ]; then
+        for _ in 1 2 3 4 5 6 7 8 9 10; do
+            [ -f {marker} ] && break
+            sleep 0.2
+        done
+    fi
+"#,
+            marker = restart::MARKER
+        )
+    } else {
+        String::new()
+    };
+
+    Ok(format!(
+        r#"MAX_RESTARTS={max_restarts}
+WINDOW={window}
+DELAY={delay}
+ON_CRASH={on_crash}
+window_start=0
+crashes=0
+
+while true; do
+    rm -f {marker}
+    {command}
+    code=$?
+{marker_wait}
+    if [ -f {marker} ]; then
+        echo "Server restarting..."
+        continue
+    fi
+    if [ $code -eq 0 ] || [ $code -eq 130 ] || [ $code -eq 143 ]; then
+        break
+    fi
+    if [ "$ON_CRASH" != "true" ]; then
+        exit $code
+    fi
+
+    now=$(date +%s)
+    if [ $((now - window_start)) -ge $WINDOW ]; then
+        window_start=$now
+        crashes=0
+    fi
+    crashes=$((crashes + 1))
+    if [ $crashes -gt $MAX_RESTARTS ]; then
+        echo "Server crashed $crashes times within ${{WINDOW}}s, not restarting again."
+        exit $code
+    fi
+
+    echo "Server exited with code $code, restarting in ${{DELAY}}s ($crashes/$MAX_RESTARTS)..."
+    sleep $DELAY
+done
+"#,
+        max_restarts = policy.max_restarts,
+        window = policy.window()?.as_secs(),
+        delay = policy.delay()?.as_secs(),
+        on_crash = policy.on_crash,
+        marker = restart::MARKER,
+        marker_wait = marker_wait,
+        command = command,
+    ))
+}
+
+fn bat_restart_loop(command: &str, policy: &RestartPolicy, wait_for_marker: bool) -> Result<String> {
+    let marker_wait = if wait_for_marker {
+        format!(
+            "if !EXIT_CODE! equ 0 if not exist {marker} timeout /t 2 /nobreak > nul\n",
+            marker = restart::MARKER
+        )
+    } else {
+        String::new()
+    };
+
+    Ok(format!(
+        r#"setlocal EnableDelayedExpansion
+set MAX_RESTARTS={max_restarts}
+set WINDOW={window}
+set DELAY={delay}
+set ON_CRASH={on_crash}
+set WINDOW_START=0
+set CRASHES=0
+
+:start
+if exist {marker} del {marker}
+{command}
+set EXIT_CODE=!errorlevel!
+{marker_wait}
+if exist {marker} (
+    echo Server restarting...
+    goto start
+)
+if !EXIT_CODE! equ 0 goto end
+if not "%ON_CRASH%" == "true" goto end
+
+for /f %%t in ('powershell -NoProfile -Command "[int][double]::Parse((Get-Date -UFormat %%s))"') do set NOW=%%t
+set /a ELAPSED=NOW - WINDOW_START
+if !ELAPSED! geq %WINDOW% (
+    set WINDOW_START=!NOW!
+    set CRASHES=0
+)
+set /a CRASHES+=1
+if !CRASHES! gtr %MAX_RESTARTS% (
+    echo Server crashed !CRASHES! times within %WINDOW%s, not restarting again.
+    goto end
+)
+
+echo Server exited with code !EXIT_CODE!, restarting in %DELAY%s (!CRASHES!/%MAX_RESTARTS%)...
+timeout /t %DELAY% /nobreak > nul
+goto start
+
+:end
+"#,
+        max_restarts = policy.max_restarts,
+        window = policy.window()?.as_secs(),
+        delay = policy.delay()?.as_secs(),
+        on_crash = policy.on_crash,
+        marker = restart::MARKER,
+        marker_wait = marker_wait,
+        command = command,
+    ))
+}
+
+// Argument files split on whitespace and treat quotes and backslashes as special,
+// so any argument with one of those is quoted with the rest escaped
+fn arg_file_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\') {
+        return arg.to_string();
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Forge reads JVM arguments from this file; it's regenerated from mcs.toml on every apply
+fn write_user_jvm_args(path: &Path, launch: &Launch) -> Result<()> {
+    let mut contents = String::from(
+        "# Generated by mcs from mcs.toml. Edit memory, flags and jvm_args there instead.\n",
+    );
+    for arg in &launch.jvm_args {
+        contents.push_str(&format!("{}\n", arg_file_quote(arg)));
+    }
+    fs::write(path.join(launch::USER_JVM_ARGS), contents)?;
+    println!("✓ Wrote {}", launch::USER_JVM_ARGS);
+    Ok(())
+}
+
+pub fn create_eula(path: &Path) -> Result<()> {
     let eula_content = r#"#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).
 eula=true
 "#;
@@ -61,3 +259,38 @@ eula=true
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bat_quoting() {
+        assert_eq!(bat_quote("-Xmx2G", false), "-Xmx2G");
+        assert_eq!(bat_quote("C:\\Program Files\\java.exe", false), "\"C:\\Program Files\\java.exe\"");
+        assert_eq!(bat_quote("-Dmotd=100%", false), "-Dmotd=100%%");
+        assert_eq!(bat_quote("say \"hi\"", false), "\"say \"\"hi\"\"\"");
+        assert_eq!(bat_quote("a^b", false), "\"a^b\"");
+        assert_eq!(bat_quote("hi!", false), "\"hi!\"");
+        assert_eq!(bat_quote("a^b", true), "\"a^^b\"");
+        assert_eq!(bat_quote("hi!", true), "\"hi^!\"");
+    }
+
+    #[test]
+    fn bat_command_escapes_for_delayed_expansion() {
+        let args = vec!["-Dx=a^b".to_string(), "hi!".to_string()];
+        assert_eq!(bat_command("java", &args, false), "java \"-Dx=a^b\" \"hi!\"");
+        assert_eq!(bat_command("java", &args, true), "java \"-Dx=a^^b\" \"hi^!\"");
+        // Carets are only stripped on lines that contain a '!'
+        assert_eq!(bat_command("java", &args[..1], true), "java \"-Dx=a^b\"");
+    }
+
+    #[test]
+    fn arg_file_quoting() {
+        assert_eq!(arg_file_quote("-Xmx2G"), "-Xmx2G");
+        assert_eq!(arg_file_quote("-Dname=a b"), "\"-Dname=a b\"");
+        assert_eq!(arg_file_quote("-Dmotd=say \"hi\""), "\"-Dmotd=say \\\"hi\\\"\"");
+        assert_eq!(arg_file_quote("-Dpath=C:\\mc"), "\"-Dpath=C:\\\\mc\"");
+        assert_eq!(arg_file_quote("-Dq=it's"), "\"-Dq=it's\"");
+    }
+}
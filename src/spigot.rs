@@ -1,26 +1,21 @@
-use anyhow::{Result, anyhow};
-use reqwest::blocking::Client;
-use serde_json::Value;
+use anyhow::{Context, Result, anyhow};
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 use crate::config::ServerConfig;
+use crate::http;
+use crate::download;
+use crate::metadata;
 
 pub fn get_available_versions() -> Result<Vec<String>> {
     // Spigot doesn't have a public API for versions, so we'll use the same versions as Vanilla
     // since Spigot supports the same Minecraft versions
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
+    let client = http::client()?;
 
-    let response = client
-        .get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
-        .send()?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Minecraft versions from Mojang API"));
-    }
-
-    let data: Value = response.json()?;
+    let data = metadata::get_json(
+        &client,
+        "https://launchermeta.mojang.com/mc/game/version_manifest.json",
+    )?
+    .ok_or_else(|| anyhow!("Failed to fetch Minecraft versions from Mojang API"))?;
     let versions = data["versions"]
         .as_array()
         .ok_or_else(|| anyhow!("Failed to parse versions from Mojang API"))?
@@ -32,26 +27,22 @@ pub fn get_available_versions() -> Result<Vec<String>> {
     Ok(versions)
 }
 
-pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
+pub fn setup_server(path: &Path, config: &ServerConfig) -> Result<()> {
     println!("\n↓ Downloading Spigot server...");
 
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
+    let client = http::client()?;
+
+    if metadata::is_offline() {
+        return Err(anyhow!("Spigot is built from source by BuildTools, which needs network access."));
+    }
 
     // Get BuildTools
     println!("■ Downloading Spigot BuildTools...");
     let buildtools_url = "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
 
-    let buildtools_response = client.get(buildtools_url).send()?;
-
-    if !buildtools_response.status().is_success() {
-        return Err(anyhow!("Failed to download Spigot BuildTools"));
-    }
-
-    let buildtools_bytes = buildtools_response.bytes()?;
     let buildtools_path = path.join("BuildTools.jar");
-    fs::write(&buildtools_path, buildtools_bytes)?;
+    download::download(&client, buildtools_url, &buildtools_path, None)
+        .context("Failed to download Spigot BuildTools")?;
 
     println!("✓ Downloaded BuildTools.jar");
     println!("\n⚠ Note: Spigot requires building from source.");
@@ -59,7 +50,7 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
     println!("■ Building Spigot {} (this happens once per version)...", config.version);
 
     // Run BuildTools to build Spigot
-    let output = std::process::Command::new("java")
+    let output = std::process::Command::new(config.java())
         .arg("-jar")
         .arg("BuildTools.jar")
         .arg("--rev")
@@ -102,7 +93,7 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
             println!("✓ Created plugins directory");
         }
         Err(e) => {
-            return Err(anyhow!("Failed to run BuildTools. Make sure Java is installed and in PATH: {}", e));
+            return Err(anyhow!("Failed to run BuildTools with {}: {}", config.java(), e));
         }
     }
 
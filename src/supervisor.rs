@@ -0,0 +1,462 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{broadcast, mpsc};
+use crate::config::ServerConfig;
+use crate::launch;
+use crate::restart;
+#[cfg(unix)]
+use crate::control;
+
+const LOG_FILE: &str = "console.log";
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+const KEPT_LOGS: u32 = 5;
+// Lines replayed to a console when it attaches
+const BACKLOG_LINES: usize = 100;
+
+pub enum Input {
+    // A line for the server console
+    Command(String),
+    // Stop the server, waiting this long (or the default) before killing it. A second
+    // stop while the first is pending kills the server right away.
+    Stop(Option<Duration>),
+}
+
+// What `mcs status` reports about a supervised server
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub pid: u32,
+    pub server_pid: Option<u32>,
+    // Unix time the current server process started
+    pub started: u64,
+    pub restarts: u32,
+}
+
+// logs/console.log, rotated to console.log.1 ... console.log.5 once it grows too large
+struct ConsoleLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl ConsoleLog {
+    fn open(server_dir: &Path) -> Result<Self> {
+        let dir = server_dir.join("logs");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open {:?}", path))?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn rotated(&self, n: u32) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        let _ = fs::remove_file(self.rotated(KEPT_LOGS));
+        for n in (1..KEPT_LOGS).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size > MAX_LOG_SIZE && self.rotate().is_err() {
+            return;
+        }
+        if writeln!(self.file, "{}", line).is_ok() {
+            self.size += line.len() as u64 + 1;
+        }
+    }
+}
+
+// Counts crashes the same way the generated start scripts do: more than max_restarts
+// within one window (starting at the first crash) and the supervisor gives up
+struct CrashCounter {
+    window_start: Option<Instant>,
+    crashes: u32,
+}
+
+impl CrashCounter {
+    fn record(&mut self, window: Duration) -> u32 {
+        let now = Instant::now();
+        if self.window_start.is_none_or(|start| now.duration_since(start) >= window) {
+            self.window_start = Some(now);
+            self.crashes = 0;
+        }
+        self.crashes += 1;
+        self.crashes
+    }
+}
+
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.interrupt.recv() => {}
+            _ = self.terminate.recv() => {}
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+fn spawn_server(path: &Path, config: &ServerConfig) -> Result<Child> {
+    let launch = launch::build(path, config)?;
+
+    let mut command = Command::new(&launch.java);
+    command
+        .args(launch.args(cfg!(windows)))
+        .envs(&launch.env)
+        .current_dir(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    // In its own process group, Ctrl+C reaches only the supervisor, which then stops
+    // the server through its console instead of the JVM being interrupted mid-save
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(windows)]
+    command.creation_flags(0x0000_0200); // CREATE_NEW_PROCESS_GROUP
+
+    command
+        .spawn()
+        .context(format!("Failed to start the server with {}", launch.java))
+}
+
+// Shared between the supervisor, its output readers and any attached consoles
+pub struct Session {
+    // Taken when the supervisor finishes, which ends every attached console
+    output: Mutex<Option<broadcast::Sender<Arc<str>>>>,
+    backlog: Mutex<VecDeque<Arc<str>>>,
+    status: Mutex<Status>,
+    log: Mutex<ConsoleLog>,
+    // Print server output to this process's stdout (`mcs run`)
+    echo: bool,
+    stop_timeout: Duration,
+}
+
+impl Session {
+    fn new(path: &Path, echo: bool, stop_timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            output: Mutex::new(Some(broadcast::channel(1024).0)),
+            backlog: Mutex::new(VecDeque::new()),
+            status: Mutex::new(Status {
+                pid: std::process::id(),
+                server_pid: None,
+                started: 0,
+                restarts: 0,
+            }),
+            log: Mutex::new(ConsoleLog::open(path)?),
+            echo,
+            stop_timeout,
+        })
+    }
+
+    fn line(&self, line: &str) {
+        if self.echo {
+            println!("{}", line);
+        }
+        if let Ok(mut log) = self.log.lock() {
+            log.write_line(line);
+        }
+        // Under the backlog lock, so an attaching console sees every line exactly once
+        if let Ok(mut backlog) = self.backlog.lock() {
+            if backlog.len() == BACKLOG_LINES {
+                backlog.pop_front();
+            }
+            let line: Arc<str> = Arc::from(line);
+            backlog.push_back(line.clone());
+            if let Some(output) = &*self.output.lock().unwrap() {
+                let _ = output.send(line);
+            }
+        }
+    }
+
+    // Messages from the supervisor itself, shown to attached consoles as well
+    fn notice(&self, message: &str) {
+        if !self.echo {
+            println!("{}", message);
+        }
+        self.line(message);
+    }
+
+    pub fn subscribe(&self) -> (Vec<Arc<str>>, broadcast::Receiver<Arc<str>>) {
+        let backlog = self.backlog.lock().unwrap();
+        let output = match &*self.output.lock().unwrap() {
+            Some(output) => output.subscribe(),
+            // Already finished: a receiver that is closed right away
+            None => broadcast::channel(1).1,
+        };
+        (backlog.iter().cloned().collect(), output)
+    }
+
+    fn close(&self) {
+        self.output.lock().unwrap().take();
+    }
+
+    pub fn status(&self) -> Status {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn set_server(&self, pid: Option<u32>) {
+        let mut status = self.status.lock().unwrap();
+        status.server_pid = pid;
+        status.started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+    }
+}
+
+fn pipe_output(stream: impl AsyncRead + Unpin + Send + 'static, session: Arc<Session>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            session.line(&line);
+        }
+    })
+}
+
+async fn send_command(stdin: &mut ChildStdin, command: &str) {
+    let _ = stdin.write_all(format!("{}\n", command.trim_end()).as_bytes()).await;
+    let _ = stdin.flush().await;
+}
+
+enum Exit {
+    Status(ExitStatus),
+    // Stopped through a signal; never restarted
+    Stopped,
+}
+
+// Runs the server once, forwarding console input until it exits
+async fn run_once(
+    path: &Path,
+    config: &ServerConfig,
+    input: &mut mpsc::Receiver<Input>,
+    session: &Arc<Session>,
+) -> Result<Exit> {
+    let mut child = spawn_server(path, config)?;
+    session.set_server(child.id());
+    let mut stdin = child.stdin.take().context("Server stdin is not available")?;
+    let stdout = child.stdout.take().context("Server stdout is not available")?;
+    let stderr = child.stderr.take().context("Server stderr is not available")?;
+    let readers = [
+        pipe_output(stdout, session.clone()),
+        pipe_output(stderr, session.clone()),
+    ];
+
+    let mut stopping = false;
+    let deadline = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(deadline);
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status?,
+            Some(input) = input.recv() => match input {
+                Input::Command(command) => send_command(&mut stdin, &command).await,
+                Input::Stop(_) if stopping => {
+                    session.notice("⚠ Killing the server");
+                    child.kill().await?;
+                }
+                Input::Stop(timeout) => {
+                    let timeout = timeout.unwrap_or(session.stop_timeout);
+                    session.notice("■ Stopping the server...");
+                    stopping = true;
+                    send_command(&mut stdin, "stop").await;
+                    deadline.as_mut().reset(tokio::time::Instant::now() + timeout);
+                }
+            },
+            _ = &mut deadline, if stopping => {
+                session.notice("⚠ The server didn't stop in time, killing it");
+                child.kill().await?;
+            }
+        }
+    };
+
+    for reader in readers {
+        let _ = reader.await;
+    }
+    session.set_server(None);
+
+    Ok(if stopping { Exit::Stopped } else { Exit::Status(status) })
+}
+
+async fn wait_for_marker(marker: &Path) -> bool {
+    let deadline = Instant::now() + restart::MARKER_WAIT;
+    while Instant::now() < deadline {
+        if marker.exists() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    marker.exists()
+}
+
+// Keeps the server running according to its restart policy until it stops
+async fn supervise(
+    path: &Path,
+    config: &ServerConfig,
+    mut input: mpsc::Receiver<Input>,
+    session: Arc<Session>,
+) -> Result<()> {
+    let mut crashes = CrashCounter {
+        window_start: None,
+        crashes: 0,
+    };
+    let marker = path.join(restart::MARKER);
+
+    loop {
+        let _ = fs::remove_file(&marker);
+        let exit = run_once(path, config, &mut input, &session).await?;
+
+        let status = match exit {
+            Exit::Stopped => {
+                session.notice("✓ Server stopped");
+                return Ok(());
+            }
+            Exit::Status(status) => status,
+        };
+
+        if marker.exists()
+            || (status.success()
+                && restart::uses_restart_script(&config.server_type)
+                && wait_for_marker(&marker).await)
+        {
+            let _ = fs::remove_file(&marker);
+            session.notice("⟳ Server restarting...");
+            continue;
+        }
+        if status.success() {
+            session.notice("✓ Server stopped");
+            return Ok(());
+        }
+
+        let code = status
+            .code()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let Some(policy) = config.restart.as_ref().filter(|p| p.on_crash) else {
+            return Err(anyhow!("Server exited with code {}", code));
+        };
+
+        let window = policy.window()?;
+        let count = crashes.record(window);
+        if count > policy.max_restarts {
+            return Err(anyhow!(
+                "Server crashed {} times within {}, not restarting again",
+                count,
+                policy.window
+            ));
+        }
+
+        let delay = policy.delay()?;
+        session.notice(&format!(
+            "⚠ Server exited with code {}, restarting in {}s ({}/{})...",
+            code,
+            delay.as_secs(),
+            count,
+            policy.max_restarts
+        ));
+        session.status.lock().unwrap().restarts += 1;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn forward_signals(input: mpsc::Sender<Input>) -> Result<()> {
+    let mut signals = Signals::new()?;
+    tokio::spawn(async move {
+        loop {
+            signals.recv().await;
+            if input.send(Input::Stop(None)).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+// Supervises the server until it stops. `attached` connects it to this terminal
+// (`mcs run`); otherwise it only talks to consoles on the control socket.
+pub fn run(path: &Path, config: &ServerConfig, stop_timeout: Duration, attached: bool) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    let result = runtime.block_on(async {
+        let session = Arc::new(Session::new(path, attached, stop_timeout)?);
+        let (input_tx, input_rx) = mpsc::channel(64);
+        forward_signals(input_tx.clone())?;
+
+        #[cfg(unix)]
+        let control = control::listen(path, input_tx.clone(), session.clone())?;
+
+        if attached {
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(tokio::io::stdin()).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if input_tx.send(Input::Command(line)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        let result = supervise(path, config, input_rx, session.clone()).await;
+
+        // Let attached consoles receive the last lines before the socket goes away
+        session.close();
+        #[cfg(unix)]
+        control.close().await;
+
+        result
+    });
+
+    // The stdin reader blocks on a read that never finishes; don't wait for it
+    runtime.shutdown_background();
+    result
+}
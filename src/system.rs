@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+#[cfg(any(unix, windows))]
+use std::process::Command;
+
+// Physical memory in bytes, or the container's limit when that is lower
+pub fn total_memory() -> Option<u64> {
+    let physical = physical_memory()?;
+    Some(match cgroup_limit() {
+        Some(limit) if limit < physical => limit,
+        _ => physical,
+    })
+}
+
+pub fn cpu_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+#[cfg(target_os = "linux")]
+fn physical_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn physical_memory() -> Option<u64> {
+    let output = Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(windows)]
+fn physical_memory() -> Option<u64> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+        ])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn physical_memory() -> Option<u64> {
+    None
+}
+
+// Resident memory of a running process in bytes
+#[cfg(unix)]
+pub fn process_memory(pid: u32) -> Option<u64> {
+    let output = Command::new("ps")
+        .args(["-o", "rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let kib: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(unix))]
+pub fn process_memory(_pid: u32) -> Option<u64> {
+    None
+}
+
+// Whether a JVM runs with `dir` as its working directory, which is how a server
+// started by start.sh or a panel shows up. Only known on Linux.
+#[cfg(target_os = "linux")]
+pub fn java_running_in(dir: &Path) -> bool {
+    let (Ok(dir), Ok(entries)) = (fs::canonicalize(dir), fs::read_dir("/proc")) else {
+        return false;
+    };
+    entries.filter_map(|e| e.ok()).any(|entry| {
+        let process = entry.path();
+        entry.file_name().to_str().is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+            && fs::read_to_string(process.join("comm")).is_ok_and(|comm| comm.trim() == "java")
+            && fs::read_link(process.join("cwd")).is_ok_and(|cwd| cwd == dir)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn java_running_in(_dir: &Path) -> bool {
+    false
+}
+
+// cgroup v2 reports "max" when unlimited, v1 a huge number
+fn cgroup_limit() -> Option<u64> {
+    ["/sys/fs/cgroup/memory.max", "/sys/fs/cgroup/memory/memory.limit_in_bytes"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok()?.trim().parse().ok())
+}
@@ -0,0 +1,12 @@
+use crate::config::{FlagPreset, ServerConfig, ServerType};
+use tempfile::TempDir;
+
+// A server config with 2G of memory and no flag preset
+pub fn config(server_type: ServerType, version: &str) -> ServerConfig {
+    ServerConfig::new(version.to_string(), server_type, "2G".parse().unwrap(), FlagPreset::None)
+}
+
+// A fresh directory, unique across test processes and removed when dropped
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new().prefix("mcs-test-").tempdir().unwrap()
+}
@@ -0,0 +1,146 @@
+use anyhow::{Result, anyhow};
+use std::time::Duration;
+
+// Parses durations like "30s", "10m", "12h", "7d" or "2w"
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let value: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration '{}'. Use e.g. 30s, 10m, 12h or 7d.", s))?;
+
+    let seconds = match unit.trim() {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow!("Invalid duration '{}'. Use e.g. 30s, 10m, 12h or 7d.", s)),
+    };
+
+    Ok(Duration::from_secs(value * seconds))
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const GIB: u64 = 1024 * MIB;
+const TIB: u64 = 1024 * GIB;
+
+// The JVM refuses heaps this small anyway
+const MIN_HEAP: u64 = 16 * MIB;
+
+// A heap size from mcs.toml: either an absolute size or a share of the machine's RAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySize {
+    Bytes(u64),
+    Percent(u8),
+}
+
+impl std::str::FromStr for MemorySize {
+    type Err = String;
+
+    // Accepts "4G", "4g", "4GB", "4 GiB", "1.5G", "512M" and "50%"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid memory size '{}' (use e.g. 512M, 4G or 50%)", s);
+        let compact: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if let Some(percent) = compact.strip_suffix('%') {
+            let percent: u8 = percent.parse().map_err(|_| invalid())?;
+            if !(1..=100).contains(&percent) {
+                return Err(format!("memory percentage must be between 1% and 100%, got '{}'", s));
+            }
+            return Ok(MemorySize::Percent(percent));
+        }
+
+        let split = compact
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(compact.len());
+        let (number, unit) = compact.split_at(split);
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+
+        let multiplier = match unit.to_ascii_lowercase().as_str() {
+            "k" | "kb" | "kib" => KIB,
+            "m" | "mb" | "mib" => MIB,
+            "g" | "gb" | "gib" => GIB,
+            "t" | "tb" | "tib" => TIB,
+            "" => return Err(format!("memory size '{}' needs a unit, e.g. {}G or {}M", s, number, number)),
+            _ => return Err(invalid()),
+        };
+
+        let bytes = (value * multiplier as f64) as u64;
+        if bytes < MIN_HEAP {
+            return Err(format!("memory size '{}' is too small, use at least 16M", s));
+        }
+        // The JVM only takes whole kilobytes
+        Ok(MemorySize::Bytes(bytes / KIB * KIB))
+    }
+}
+
+impl std::fmt::Display for MemorySize {
+    // In the JVM's own notation, using the largest unit that divides evenly
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            MemorySize::Percent(percent) => write!(f, "{}%", percent),
+            MemorySize::Bytes(bytes) if bytes % TIB == 0 => write!(f, "{}T", bytes / TIB),
+            MemorySize::Bytes(bytes) if bytes % GIB == 0 => write!(f, "{}G", bytes / GIB),
+            MemorySize::Bytes(bytes) if bytes % MIB == 0 => write!(f, "{}M", bytes / MIB),
+            MemorySize::Bytes(bytes) => write!(f, "{}K", bytes / KIB),
+        }
+    }
+}
+
+impl serde::Serialize for MemorySize {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MemorySize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl MemorySize {
+    pub fn from_gib(gib: u64) -> Self {
+        MemorySize::Bytes(gib * GIB)
+    }
+
+    // Absolute size in bytes; percentages need the machine's total memory
+    pub fn bytes(&self, total_memory: Option<u64>) -> Option<u64> {
+        match *self {
+            MemorySize::Bytes(bytes) => Some(bytes),
+            MemorySize::Percent(percent) => total_memory.map(|total| total / 100 * percent as u64),
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use crate::checksum::{self, Algorithm};
+use crate::compat;
+use crate::config::{ServerConfig, ServerType};
+use crate::control;
+use crate::jar::{self, JarKind, file_name};
+use crate::modrinth::{self, ModrinthFile};
+use crate::version;
+use crate::{fabric, forge, paper, spigot, vanilla};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUP_DIR: &str = ".mcs-upgrade";
+// Files setup rewrites, restored as a whole on rollback
+const SERVER_FILES: &[&str] = &[
+    "server.jar",
+    "mcs.toml",
+    "mcs.lock",
+    "server.properties",
+    "eula.txt",
+    "start.sh",
+    "start.bat",
+    "run.sh",
+    "run.bat",
+    "user_jvm_args.txt",
+    "restart.cmd",
+    "spigot.yml",
+];
+
+pub enum Status {
+    // A build for the target version exists on Modrinth
+    Update(String, ModrinthFile),
+    // Not on Modrinth, but the jar itself declares support for the target version
+    Keep,
+    Blocked(String),
+}
+
+pub struct PlanEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub current: Option<String>,
+    pub status: Status,
+}
+
+pub struct Plan {
+    pub target: ServerConfig,
+    pub entries: Vec<PlanEntry>,
+}
+
+impl Plan {
+    pub fn blocked(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, Status::Blocked(_)))
+            .count()
+    }
+}
+
+fn available_versions(server_type: &ServerType) -> Result<Vec<String>> {
+    match server_type {
+        ServerType::Paper => paper::get_available_versions(),
+        ServerType::Vanilla => vanilla::get_available_versions(),
+        ServerType::Fabric => fabric::get_available_versions(),
+        ServerType::Spigot => spigot::get_available_versions(),
+        ServerType::Forge => forge::get_available_versions(),
+    }
+}
+
+pub fn build_plan(path: &Path, config: &ServerConfig, target_version: &str) -> Result<Plan> {
+    println!(
+        "⟳ Checking {} support for Minecraft {}...",
+        config.server_type, target_version
+    );
+    if !available_versions(&config.server_type)?
+        .iter()
+        .any(|v| v == target_version)
+    {
+        return Err(anyhow!(
+            "{} is not available for Minecraft {}",
+            config.server_type,
+            target_version
+        ));
+    }
+
+    let mut target = config.clone();
+    target.version = target_version.to_string();
+
+    let jars = compat::installed_jars(path, config);
+    let Some(loader) = config.server_type.modrinth_loader() else {
+        return Ok(Plan {
+            target,
+            entries: vec![],
+        });
+    };
+
+    let hashes = jars
+        .iter()
+        .map(|p| checksum::hash_file(p, Algorithm::Sha1))
+        .collect::<Result<Vec<_>>>()?;
+
+    println!(
+        "⟳ Querying Modrinth for {} installed file(s)...",
+        jars.len()
+    );
+    let (known, updates) = if hashes.is_empty() {
+        Default::default()
+    } else {
+        (
+            modrinth::get_versions_from_hashes(&hashes)?,
+            modrinth::get_latest_versions_from_hashes(&hashes, loader, target_version)?,
+        )
+    };
+
+    let mut entries = Vec::new();
+    for (jar_path, hash) in jars.into_iter().zip(&hashes) {
+        let inspected = jar::inspect(&jar_path);
+        let metadata = inspected.as_ref().ok().and_then(Option::as_ref);
+        let name = metadata
+            .map(|m| m.name.clone().unwrap_or_else(|| m.id.clone()))
+            .unwrap_or_else(|| file_name(&jar_path));
+
+        let (current, status) = match (known.get(hash), updates.get(hash)) {
+            (Some(current), Some(latest))
+                if latest.game_versions.iter().any(|v| v == target_version) =>
+            {
+                let file = latest
+                    .files
+                    .iter()
+                    .find(|f| f.primary)
+                    .or_else(|| latest.files.first());
+                let status = match file {
+                    Some(file) => Status::Update(latest.version_number.clone(), file.clone()),
+                    None => Status::Blocked("No downloadable file found".to_string()),
+                };
+                (Some(current.version_number.clone()), status)
+            }
+            (Some(current), _) => (
+                Some(current.version_number.clone()),
+                Status::Blocked(format!("No build for Minecraft {}", target_version)),
+            ),
+            (None, _) if let Err(e) = &inspected => (
+                None,
+                Status::Blocked(format!("Unreadable jar ({})", e.root_cause())),
+            ),
+            (None, _) => {
+                let declared = metadata.and_then(|m| {
+                    let maven = matches!(m.kind, JarKind::Forge | JarKind::NeoForge);
+                    m.minecraft
+                        .as_deref()
+                        .and_then(|c| version::matches(c, target_version, maven))
+                });
+                let status = match declared {
+                    Some(true) => Status::Keep,
+                    Some(false) => Status::Blocked(format!(
+                        "Not on Modrinth and declares Minecraft {}",
+                        metadata
+                            .and_then(|m| m.minecraft.clone())
+                            .unwrap_or_default()
+                    )),
+                    None => Status::Blocked(
+                        "Not on Modrinth and declares no Minecraft version".to_string(),
+                    ),
+                };
+                (metadata.and_then(|m| m.version.clone()), status)
+            }
+        };
+
+        entries.push(PlanEntry {
+            path: jar_path,
+            name,
+            current,
+            status,
+        });
+    }
+
+    Ok(Plan { target, entries })
+}
+
+pub fn print_plan(config: &ServerConfig, plan: &Plan) {
+    println!(
+        "\n▶ Upgrade plan: {} {} → {}\n",
+        config.server_type, config.version, plan.target.version
+    );
+
+    for entry in &plan.entries {
+        let current = entry.current.as_deref().unwrap_or("?");
+        match &entry.status {
+            Status::Update(new_version, _) => {
+                println!("  ✓ {} {} → {}", entry.name, current, new_version)
+            }
+            Status::Keep => println!("  ✓ {} {} (kept as is)", entry.name, current),
+            Status::Blocked(reason) => {
+                println!("  ✗ {} {} ({})", entry.name, current, reason)
+            }
+        }
+    }
+
+    let blocked = plan.blocked();
+    println!(
+        "\n{} ready, {} blocked",
+        plan.entries.len() - blocked,
+        blocked
+    );
+}
+
+// Swaps the server jar and every planned file. Any failure restores the previous
+// server jar, config and content and removes what the upgrade added before
+// returning the error.
+pub fn execute(
+    path: &Path,
+    config: &ServerConfig,
+    plan: &Plan,
+    setup_server: impl Fn(&Path, &ServerConfig) -> Result<()>,
+) -> Result<()> {
+    if control::is_running(path) {
+        return Err(anyhow!("The server is running. Stop it before upgrading."));
+    }
+
+    let backup = path.join(BACKUP_DIR);
+    if backup.exists() {
+        fs::remove_dir_all(&backup)?;
+    }
+    let staging = backup.join("new");
+    let old = backup.join("old");
+    fs::create_dir_all(&staging)?;
+    fs::create_dir_all(&old)?;
+
+    // Stage new content first; failing here leaves the server untouched
+    let downloads: Vec<_> = plan
+        .entries
+        .iter()
+        .filter_map(|entry| match &entry.status {
+            Status::Update(_, file) => Some((file.clone(), staging.join(&file.filename))),
+            _ => None,
+        })
+        .collect();
+    if !downloads.is_empty() {
+        println!("↓ Downloading {} file(s)...", downloads.len());
+    }
+    if let Err(e) = modrinth::download_files(downloads) {
+        let _ = fs::remove_dir_all(&backup);
+        return Err(e);
+    }
+
+    let files = server_files(config, &plan.target);
+    for file in &files {
+        if path.join(file).is_file() {
+            let saved = old.join(file);
+            if let Some(parent) = saved.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path.join(file), saved)?;
+        }
+    }
+
+    let roots = tracked(config, &files);
+    let before = snapshot(path, &roots)?;
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = swap(path, plan, &staging, &old, &mut moved, &setup_server);
+
+    if let Err(e) = result {
+        println!("\n⚠ Upgrade failed, rolling back...");
+        let failures = rollback(path, &old, &moved, &files, &roots, &before);
+        if !failures.is_empty() {
+            for failure in &failures {
+                println!("  ✗ {}", failure);
+            }
+            println!("→ The previous files are kept in {}", old.display());
+            return Err(e.context("Upgrade failed and the rollback was incomplete"));
+        }
+        let _ = fs::remove_dir_all(&backup);
+        println!("✓ Restored {} {}", config.server_type, config.version);
+        return Err(e);
+    }
+
+    let _ = fs::remove_dir_all(&backup);
+    Ok(())
+}
+
+// SERVER_FILES plus the YAML files [files] overlays write, before and after
+fn server_files(config: &ServerConfig, target: &ServerConfig) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = SERVER_FILES.iter().map(PathBuf::from).collect();
+    for file in config.files.keys().chain(target.files.keys()) {
+        if !files.iter().any(|f| f == Path::new(file)) {
+            files.push(PathBuf::from(file));
+        }
+    }
+    files
+}
+
+// What an upgrade can add to: the server files, libraries/ and the content directory.
+// Worlds and everything else are never looked at.
+fn tracked(config: &ServerConfig, files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots = files.to_vec();
+    roots.push(PathBuf::from("libraries"));
+    if let Some(dir) = config.server_type.content_dir() {
+        roots.push(PathBuf::from(dir));
+    }
+    roots
+}
+
+// Every existing path under the tracked roots, so a rollback can remove whatever
+// the failed upgrade added (a new Forge libraries/ tree, user_jvm_args.txt, ...)
+fn snapshot(path: &Path, roots: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+    let mut paths = HashSet::new();
+    let mut pending: Vec<PathBuf> = roots.iter().map(|root| path.join(root)).collect();
+    while let Some(current) = pending.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&current) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            for entry in fs::read_dir(&current).context(format!("Failed to read {:?}", current))? {
+                pending.push(entry?.path());
+            }
+        }
+        paths.insert(current);
+    }
+    Ok(paths)
+}
+
+fn swap(
+    path: &Path,
+    plan: &Plan,
+    staging: &Path,
+    old: &Path,
+    moved: &mut Vec<(PathBuf, PathBuf)>,
+    setup_server: &impl Fn(&Path, &ServerConfig) -> Result<()>,
+) -> Result<()> {
+    // Saved first so setup can record what it picks (like the Java runtime) on top
+    plan.target.save(path)?;
+    setup_server(path, &plan.target)?;
+
+    for entry in &plan.entries {
+        let dest_dir = entry
+            .path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid path {:?}", entry.path))?;
+
+        match &entry.status {
+            Status::Update(_, file) => {
+                let backup_path = old.join(file_name(&entry.path));
+                fs::rename(&entry.path, &backup_path)?;
+                moved.push((entry.path.clone(), backup_path));
+
+                let new_path = dest_dir.join(&file.filename);
+                fs::rename(staging.join(&file.filename), &new_path)?;
+                moved.push((new_path, PathBuf::new()));
+            }
+            Status::Blocked(_) => {
+                let disabled = compat::disable(&entry.path)?;
+                moved.push((entry.path.clone(), disabled));
+            }
+            Status::Keep => {}
+        }
+    }
+
+    if let Some(dest_subdir) = plan.target.server_type.content_dir() {
+        for entry in &plan.entries {
+            if let Status::Update(_, file) = &entry.status
+                && let Some(hash) = file.checksum()
+            {
+                checksum::record(path, &Path::new(dest_subdir).join(&file.filename), &hash)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Restores as much as possible and returns what couldn't be restored
+fn rollback(
+    path: &Path,
+    old: &Path,
+    moved: &[(PathBuf, PathBuf)],
+    files: &[PathBuf],
+    roots: &[PathBuf],
+    before: &HashSet<PathBuf>,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    // Undo in reverse so a new file with the same name as an old one is removed first
+    for (original, backup_path) in moved.iter().rev() {
+        if backup_path.as_os_str().is_empty() {
+            if let Err(e) = fs::remove_file(original) {
+                failures.push(format!("Failed to remove {:?}: {}", original, e));
+            }
+        } else if let Err(e) = fs::rename(backup_path, original) {
+            failures.push(format!("Failed to restore {:?}: {}", original, e));
+        }
+    }
+
+    for file in files {
+        if old.join(file).exists()
+            && let Err(e) = fs::copy(old.join(file), path.join(file))
+        {
+            failures.push(format!("Failed to restore {}: {}", file.display(), e));
+        }
+    }
+
+    // Anything under the tracked roots that didn't exist before was created by the upgrade
+    let mut pending: Vec<PathBuf> = roots.iter().map(|root| path.join(root)).collect();
+    while let Some(current) = pending.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&current) else {
+            continue;
+        };
+        if before.contains(&current) {
+            if metadata.is_dir() {
+                match fs::read_dir(&current) {
+                    Ok(entries) => pending.extend(entries.filter_map(|e| e.ok()).map(|e| e.path())),
+                    Err(e) => failures.push(format!("Failed to read {:?}: {}", current, e)),
+                }
+            }
+            continue;
+        }
+        let removed = if metadata.is_dir() {
+            fs::remove_dir_all(&current)
+        } else {
+            fs::remove_file(&current)
+        };
+        if let Err(e) = removed {
+            failures.push(format!("Failed to remove {:?}: {}", current, e));
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{config, temp_dir};
+
+    #[test]
+    fn rollback_restores_files_and_removes_new_ones() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::create_dir_all(dir.join("libraries/old")).unwrap();
+        fs::write(dir.join("server.jar"), "old server").unwrap();
+        fs::write(dir.join("mods/a.jar"), "a").unwrap();
+        fs::write(dir.join("server.properties"), "motd=old\n").unwrap();
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config/forge.yml"), "a: 1\n").unwrap();
+        fs::create_dir_all(dir.join("world")).unwrap();
+
+        let mut config = config(ServerType::Forge, "1.20.1");
+        config.files.insert("config/forge.yml".to_string(), toml::Table::new());
+        config.save(dir).unwrap();
+        let mut target = config.clone();
+        target.version = "1.21".to_string();
+        let plan = Plan {
+            target,
+            entries: ["a.jar", "missing.jar"]
+                .into_iter()
+                .map(|name| PlanEntry {
+                    path: dir.join("mods").join(name),
+                    name: name.to_string(),
+                    current: None,
+                    status: Status::Blocked(String::new()),
+                })
+                .collect(),
+        };
+
+        // Setup succeeds, then disabling missing.jar fails after a.jar was disabled
+        let result = execute(dir, &config, &plan, |path, _| {
+            fs::write(path.join("server.jar"), "new server")?;
+            fs::write(path.join("user_jvm_args.txt"), "-Xmx2G")?;
+            fs::create_dir_all(path.join("libraries/new/deep"))?;
+            fs::write(path.join("server.properties"), "motd=new\n")?;
+            fs::write(path.join("config/forge.yml"), "a: 2\n")?;
+            // Outside what an upgrade touches, so left alone
+            fs::write(path.join("world/level.dat"), "level")?;
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(dir.join("server.jar")).unwrap(), "old server");
+        assert_eq!(ServerConfig::load(dir).unwrap().version, "1.20.1");
+        assert!(dir.join("mods/a.jar").exists());
+        assert!(!dir.join("mods/a.jar.disabled").exists());
+        assert!(!dir.join("user_jvm_args.txt").exists());
+        assert!(!dir.join("libraries/new").exists());
+        assert!(dir.join("libraries/old").exists());
+        assert_eq!(fs::read_to_string(dir.join("server.properties")).unwrap(), "motd=old\n");
+        assert_eq!(fs::read_to_string(dir.join("config/forge.yml")).unwrap(), "a: 1\n");
+        assert!(dir.join("world/level.dat").exists());
+        assert!(!dir.join(BACKUP_DIR).exists());
+    }
+}
@@ -1,24 +1,19 @@
 use anyhow::{Result, anyhow};
-use reqwest::blocking::Client;
-use serde_json::Value;
-use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
+use crate::checksum::{self, Algorithm, Checksum};
 use crate::config::ServerConfig;
+use crate::http;
+use crate::cache;
+use crate::metadata;
 
 pub fn get_available_versions() -> Result<Vec<String>> {
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
+    let client = http::client()?;
 
-    let response = client
-        .get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
-        .send()?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("Failed to fetch Vanilla versions from Mojang API"));
-    }
-
-    let data: Value = response.json()?;
+    let data = metadata::get_json(
+        &client,
+        "https://launchermeta.mojang.com/mc/game/version_manifest.json",
+    )?
+    .ok_or_else(|| anyhow!("Failed to fetch Vanilla versions from Mojang API"))?;
     let versions = data["versions"]
         .as_array()
         .ok_or_else(|| anyhow!("Failed to parse versions from Mojang API"))?
@@ -30,22 +25,15 @@ pub fn get_available_versions() -> Result<Vec<String>> {
     Ok(versions)
 }
 
-pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
-    println!("\n↓ Downloading Vanilla server...");
-
-    let client = Client::builder()
-        .user_agent("mcs/1.0.0 (github.com/user/mcs)")
-        .build()?;
-
-    let manifest_response = client
-        .get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
-        .send()?;
-
-    if !manifest_response.status().is_success() {
-        return Err(anyhow!("Failed to fetch version manifest from Mojang"));
-    }
+// The per-release version JSON from Mojang's manifest (downloads, javaVersion, ...)
+pub fn get_version_info(version: &str) -> Result<serde_json::Value> {
+    let client = http::client()?;
 
-    let manifest: Value = manifest_response.json()?;
+    let manifest = metadata::get_json(
+        &client,
+        "https://launchermeta.mojang.com/mc/game/version_manifest.json",
+    )?
+    .ok_or_else(|| anyhow!("Failed to fetch version manifest from Mojang"))?;
 
     let versions = manifest["versions"]
         .as_array()
@@ -53,34 +41,39 @@ pub fn setup_server(path: &PathBuf, config: &ServerConfig) -> Result<()> {
 
     let version_data = versions
         .iter()
-        .find(|v| v["id"].as_str() == Some(&config.version))
-        .ok_or_else(|| anyhow!("Version {} not found", config.version))?;
+        .find(|v| v["id"].as_str() == Some(version))
+        .ok_or_else(|| anyhow!("Version {} not found", version))?;
 
     let version_url = version_data["url"]
         .as_str()
         .ok_or_else(|| anyhow!("Failed to get version URL"))?;
 
-    println!("■ Found version {}", config.version);
+    metadata::get_json(&client, version_url)?
+        .ok_or_else(|| anyhow!("Failed to fetch version details"))
+}
 
-    let version_response = client.get(version_url).send()?;
+pub fn setup_server(path: &Path, config: &ServerConfig) -> Result<()> {
+    println!("\n↓ Downloading Vanilla server...");
 
-    if !version_response.status().is_success() {
-        return Err(anyhow!("Failed to fetch version details"));
-    }
+    let client = http::client()?;
 
-    let version_info: Value = version_response.json()?;
+    let version_info = get_version_info(&config.version)?;
+    println!("■ Found version {}", config.version);
 
     let server_url = version_info["downloads"]["server"]["url"]
         .as_str()
         .ok_or_else(|| anyhow!("Server download URL not found for this version"))?;
 
-    let jar_response = client.get(server_url).send()?;
-    let jar_bytes = jar_response.bytes()?;
+    let sha1 = version_info["downloads"]["server"]["sha1"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Server download checksum not found for this version"))?;
+    let expected = Checksum::new(Algorithm::Sha1, sha1);
 
     let server_jar_path = path.join("server.jar");
-    fs::write(&server_jar_path, jar_bytes)?;
+    cache::fetch(&client, server_url, &server_jar_path, Some(&expected))?;
+    checksum::record(path, Path::new("server.jar"), &expected)?;
 
-    println!("✓ Downloaded server.jar");
+    println!("✓ Downloaded server.jar (sha1 verified)");
 
     Ok(())
 }
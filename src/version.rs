@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+
+#[derive(PartialEq, Eq, Debug)]
+enum Suffix {
+    Release,
+    Rc(u64),
+    Pre(u64),
+}
+
+impl Ord for Suffix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |s: &Suffix| match s {
+            Suffix::Release => 2,
+            Suffix::Rc(_) => 1,
+            Suffix::Pre(_) => 0,
+        };
+        match (self, other) {
+            (Suffix::Rc(a), Suffix::Rc(b)) | (Suffix::Pre(a), Suffix::Pre(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for Suffix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(PartialEq, Eq)]
+struct Part {
+    num: u64,
+    suffix: Suffix,
+}
+
+impl Ord for Part {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.num
+            .cmp(&other.num)
+            .then_with(|| self.suffix.cmp(&other.suffix))
+    }
+}
+
+impl PartialOrd for Part {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn parse_part(seg: &str) -> Part {
+    let num_len = seg
+        .bytes()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let num: u64 = seg[..num_len].parse().unwrap_or(0);
+    let rest = seg[num_len..].trim_start_matches('-');
+
+    let suffix = if rest.is_empty() {
+        Suffix::Release
+    } else if let Some(n) = rest.strip_prefix("rc") {
+        Suffix::Rc(n.trim_start_matches('-').parse().unwrap_or(0))
+    } else if let Some(n) = rest.strip_prefix("pre") {
+        Suffix::Pre(n.trim_start_matches('-').parse().unwrap_or(0))
+    } else {
+        Suffix::Pre(0)
+    };
+
+    Part { num, suffix }
+}
+
+// Missing trailing components count as 0, so 1.20 and 1.20.0 are the same version
+pub fn cmp_versions(a: &str, b: &str) -> Ordering {
+    let a_parts: Vec<Part> = a.split('.').map(parse_part).collect();
+    let b_parts: Vec<Part> = b.split('.').map(parse_part).collect();
+    let zero = Part { num: 0, suffix: Suffix::Release };
+
+    for i in 0..a_parts.len().max(b_parts.len()) {
+        let x = a_parts.get(i).unwrap_or(&zero);
+        let y = b_parts.get(i).unwrap_or(&zero);
+        match x.cmp(y) {
+            Ordering::Equal => continue,
+            o => return o,
+        }
+    }
+    Ordering::Equal
+}
+
+// Weekly snapshots like `23w51a` don't order against release numbers
+fn is_snapshot(version: &str) -> bool {
+    let bytes = version.as_bytes();
+    bytes.len() > 5
+        && bytes[..2].iter().all(u8::is_ascii_digit)
+        && bytes[2] == b'w'
+        && bytes[3..5].iter().all(u8::is_ascii_digit)
+        && bytes[5..].iter().all(u8::is_ascii_lowercase)
+}
+
+// Checks `version` against a constraint written in a loader's range syntax.
+// Returns None when the constraint can't be understood, so callers can treat it
+// as "unknown" rather than "incompatible".
+pub fn matches(constraint: &str, version: &str, maven: bool) -> Option<bool> {
+    let constraint = constraint.trim();
+    if constraint.is_empty() || constraint == "*" {
+        return Some(true);
+    }
+    if is_snapshot(version) {
+        return None;
+    }
+    if maven {
+        matches_maven(constraint, version)
+    } else {
+        matches_predicates(constraint, version)
+    }
+}
+
+// Fabric/Quilt style: `||` separates alternatives, whitespace separates ANDed comparators
+fn matches_predicates(constraint: &str, version: &str) -> Option<bool> {
+    let mut any = false;
+    for alternative in constraint.split("||") {
+        let mut all = true;
+        for predicate in alternative.split_whitespace() {
+            all &= matches_predicate(predicate, version)?;
+        }
+        any |= all;
+    }
+    Some(any)
+}
+
+fn matches_predicate(predicate: &str, version: &str) -> Option<bool> {
+    let (op, target) = ["<=", ">=", "<", ">", "=", "~", "^"]
+        .iter()
+        .find_map(|op| predicate.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("", predicate));
+    // A trailing `-` only means "prereleases included"
+    let target = target.trim().trim_end_matches('-');
+
+    if target.is_empty() || target == "*" {
+        return Some(true);
+    }
+    if !target.starts_with(|c: char| c.is_ascii_digit()) || is_snapshot(target) {
+        return None;
+    }
+
+    let cmp = cmp_versions(version, target);
+    Some(match op {
+        "<=" => cmp != Ordering::Greater,
+        ">=" => cmp != Ordering::Less,
+        "<" => cmp == Ordering::Less,
+        ">" => cmp == Ordering::Greater,
+        "~" => cmp != Ordering::Less && cmp_versions(version, &bump(target, 1)) == Ordering::Less,
+        "^" => cmp != Ordering::Less && cmp_versions(version, &bump(target, 0)) == Ordering::Less,
+        _ => matches_wildcard(target, version),
+    })
+}
+
+// `1.20.x` / `1.20.*` match any patch release; plain versions must match exactly
+fn matches_wildcard(pattern: &str, version: &str) -> bool {
+    let mut version_parts = version.split('.');
+    for part in pattern.split('.') {
+        if part == "x" || part == "X" || part == "*" {
+            return true;
+        }
+        if version_parts.next() != Some(part) {
+            return false;
+        }
+    }
+    version_parts.next().is_none()
+}
+
+// Smallest version above every release sharing the first `index + 1` components
+fn bump(version: &str, index: usize) -> String {
+    let mut parts: Vec<u64> = version
+        .split('.')
+        .map(|p| {
+            p.bytes()
+                .take_while(|b| b.is_ascii_digit())
+                .fold(0, |acc, b| acc * 10 + u64::from(b - b'0'))
+        })
+        .collect();
+    while parts.len() <= index {
+        parts.push(0);
+    }
+    parts.truncate(index + 1);
+    parts[index] += 1;
+    parts
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Maven style used by Forge/NeoForge: `[1.20,1.21)`, `[1.20.1]`, `(,1.19]`,
+// several ranges joined by commas. A bare version is only a recommendation.
+fn matches_maven(constraint: &str, version: &str) -> Option<bool> {
+    if !constraint.starts_with(['[', '(']) {
+        return Some(true);
+    }
+
+    let mut rest = constraint;
+    let mut any = false;
+    while !rest.is_empty() {
+        let close = rest.find([']', ')'])?;
+        let range = &rest[..=close];
+        any |= matches_maven_range(range, version)?;
+        rest = rest[close + 1..].trim_start_matches([',', ' ']);
+    }
+    Some(any)
+}
+
+fn matches_maven_range(range: &str, version: &str) -> Option<bool> {
+    // Ranges come from third-party metadata, so anything malformed is "unknown"
+    let (inclusive_low, rest) = match range.strip_prefix('[') {
+        Some(rest) => (true, rest),
+        None => (false, range.strip_prefix('(')?),
+    };
+    let (inner, inclusive_high) = match rest.strip_suffix(']') {
+        Some(inner) => (inner, true),
+        None => (rest.strip_suffix(')')?, false),
+    };
+
+    let Some((low, high)) = inner.split_once(',') else {
+        return Some(cmp_versions(version, inner.trim()) == Ordering::Equal);
+    };
+    let (low, high) = (low.trim(), high.trim());
+
+    if !low.is_empty() {
+        match cmp_versions(version, low) {
+            Ordering::Less => return Some(false),
+            Ordering::Equal if !inclusive_low => return Some(false),
+            _ => {}
+        }
+    }
+    if !high.is_empty() {
+        match cmp_versions(version, high) {
+            Ordering::Greater => return Some(false),
+            Ordering::Equal if !inclusive_high => return Some(false),
+            _ => {}
+        }
+    }
+    Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(cmp_versions("1.20.1", "1.20.1"), Ordering::Equal);
+        assert_eq!(cmp_versions("1.20.10", "1.20.9"), Ordering::Greater);
+        assert_eq!(cmp_versions("1.20", "1.20.1"), Ordering::Less);
+        assert_eq!(cmp_versions("1.21", "1.20.6"), Ordering::Greater);
+        assert_eq!(cmp_versions("1.20", "1.20.0"), Ordering::Equal);
+        assert_eq!(cmp_versions("1.20.0.0", "1.20"), Ordering::Equal);
+        assert_eq!(matches(">=1.20.0", "1.20", false), Some(true));
+        assert_eq!(matches("[1.20.0,1.21)", "1.20", true), Some(true));
+    }
+
+    #[test]
+    fn compares_pre_releases() {
+        assert_eq!(cmp_versions("1.20.5-pre1", "1.20.5"), Ordering::Less);
+        assert_eq!(cmp_versions("1.20.5-rc1", "1.20.5-pre3"), Ordering::Greater);
+        assert_eq!(cmp_versions("1.20.5-pre2", "1.20.5-pre10"), Ordering::Less);
+        assert_eq!(cmp_versions("1.20.5-rc1", "1.20.4"), Ordering::Greater);
+    }
+
+    #[test]
+    fn predicates() {
+        assert_eq!(matches(">=1.20", "1.20.1", false), Some(true));
+        assert_eq!(matches(">=1.20.2", "1.20.1", false), Some(false));
+        assert_eq!(matches("<1.21", "1.20.6", false), Some(true));
+        assert_eq!(matches("<1.21", "1.21", false), Some(false));
+        assert_eq!(matches("<=1.21", "1.21", false), Some(true));
+        assert_eq!(matches(">1.20.1", "1.20.1", false), Some(false));
+        assert_eq!(matches(">=1.20 <1.21", "1.20.4", false), Some(true));
+        assert_eq!(matches(">=1.20 <1.21", "1.21.1", false), Some(false));
+        assert_eq!(matches("1.20.3 || 1.20.4", "1.20.4", false), Some(true));
+        assert_eq!(matches("1.20.3 || 1.20.4", "1.20.5", false), Some(false));
+        assert_eq!(matches("~1.20.1", "1.20.6", false), Some(true));
+        assert_eq!(matches("~1.20.1", "1.21", false), Some(false));
+        assert_eq!(matches("^1.20", "1.21.4", false), Some(true));
+    }
+
+    #[test]
+    fn open_ended_and_unknown() {
+        assert_eq!(matches("*", "1.8.9", false), Some(true));
+        assert_eq!(matches("", "1.8.9", true), Some(true));
+        assert_eq!(matches(">=1.20-", "1.20", false), Some(true));
+        // Snapshot names can't be compared, which is "unknown" rather than "incompatible"
+        assert_eq!(matches(">=23w51a", "1.21", false), None);
+        assert_eq!(matches(">=1.20", "24w14a", false), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(matches("1.20.x", "1.20.4", false), Some(true));
+        assert_eq!(matches("1.20.x", "1.20", false), Some(true));
+        assert_eq!(matches("1.20.*", "1.21", false), Some(false));
+        assert_eq!(matches("1.20.1", "1.20.10", false), Some(false));
+    }
+
+    #[test]
+    fn pre_release_predicates() {
+        assert_eq!(matches(">=1.20.5", "1.20.5-pre1", false), Some(false));
+        assert_eq!(matches("<1.20.5", "1.20.5-rc1", false), Some(true));
+        assert_eq!(matches("[1.20.5,)", "1.20.5-pre1", true), Some(false));
+    }
+
+    #[test]
+    fn maven_bounds() {
+        assert_eq!(matches("[1.20,1.21)", "1.20", true), Some(true));
+        assert_eq!(matches("[1.20,1.21)", "1.21", true), Some(false));
+        assert_eq!(matches("(1.20,1.21]", "1.20", true), Some(false));
+        assert_eq!(matches("(1.20,1.21]", "1.21", true), Some(true));
+        assert_eq!(matches("[1.20.1]", "1.20.1", true), Some(true));
+        assert_eq!(matches("[1.20.1]", "1.20.2", true), Some(false));
+    }
+
+    #[test]
+    fn maven_open_ended() {
+        assert_eq!(matches("[47,)", "47.2.0", true), Some(true));
+        assert_eq!(matches("[47,)", "46.0.1", true), Some(false));
+        assert_eq!(matches("(,1.19]", "1.18.2", true), Some(true));
+        assert_eq!(matches("(,1.19)", "1.19", true), Some(false));
+        assert_eq!(matches("(,1.16],[1.18,)", "1.17.1", true), Some(false));
+        assert_eq!(matches("(,1.16],[1.18,)", "1.18.2", true), Some(true));
+        // A bare version is only a recommendation
+        assert_eq!(matches("1.20.1", "1.19", true), Some(true));
+        assert_eq!(matches("[1.20", "1.20", true), None);
+    }
+
+    #[test]
+    fn malformed_maven_ranges() {
+        assert_eq!(matches("[1.20,1.21)]", "1.20.1", true), None);
+        assert_eq!(matches("[1.20,1.21)x]", "1.20.1", true), None);
+        assert_eq!(matches("[é]", "1.20.1", true), Some(false));
+        assert_eq!(matches("[]", "1.20.1", true), Some(false));
+    }
+}